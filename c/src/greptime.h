@@ -18,13 +18,30 @@
 #include <stdint.h>
 #include <stdio.h>
 
+// Status codes returned by all functions. Failed requests are reported with the most precise
+// code decoded from the server error, and Unknown if none applies.
 enum Status {
     Ok = 0,
     Unknown = 1000,
+    // The server cannot be reached, or it is temporarily unable to serve the request.
     ServerUnavailable = 1001,
     InvalidArgument = 1002,
     InvalidPointer = 1003,
     IllegalState = 1004,
+    Timeout = 1005,
+    // Missing or wrong credentials.
+    Unauthenticated = 1006,
+    // The user is not allowed to access the database or table.
+    PermissionDenied = 1007,
+    // The table or database of the request does not exist.
+    TableNotFound = 1008,
+    // Columns of the request conflict with the schema of the existing table.
+    SchemaConflict = 1009,
+    // The request exceeds the message size limit of the client or the server.
+    RequestTooLarge = 1010,
+    // A bug in the library, see last_error_message. Objects passed to the failed call may be
+    // left in an inconsistent state and should be freed.
+    Panic = 1011,
 };
 
 enum SemanticType {
@@ -47,10 +64,41 @@ enum DataType {
     Float64 = 10,
     Binary = 11,
     String = 12,
+    Date = 13,
+    Datetime = 14,
     TimestampSecond = 15,
     TimestampMillisecond = 16,
     TimestampMicrosecond = 17,
     TimestampNanosecond = 18,
+    TimeSecond = 19,
+    TimeMillisecond = 20,
+    TimeMicrosecond = 21,
+    TimeNanosecond = 22,
+    IntervalYearMonth = 23,
+    IntervalDayTime = 24,
+    IntervalMonthDayNano = 25,
+    Decimal128 = 30,
+    Json = 31,
+    Vector = 32,
+};
+
+// Indexes to create on a column when the table is auto-created.
+enum ColumnIndex {
+    InvertedIndex = 1,
+    FulltextIndex = 2,
+    SkippingIndex = 4,
+};
+
+enum FulltextAnalyzer {
+    English = 0,
+    Chinese = 1,
+};
+
+// Compression of Arrow Flight messages sent by bulk writers.
+enum Compression {
+    NoCompression = 0,
+    Lz4 = 1,
+    Zstd = 2,
 };
 
 typedef struct {
@@ -58,6 +106,24 @@ typedef struct {
     size_t len;
 } BinaryValue;
 
+typedef struct {
+    int32_t months;
+    int32_t days;
+    int64_t nanoseconds;
+} IntervalMonthDayNanoValue;
+
+// Unscaled 128-bit two's complement value of a decimal, split into high and
+// low 64 bits. For example, 12.34 in a column with scale 2 is {.hi = 0, .lo = 1234}.
+typedef struct {
+    int64_t hi;
+    int64_t lo;
+} Decimal128Value;
+
+typedef struct {
+    const float* data;
+    size_t len;
+} VectorValue;
+
 typedef union {
     bool boolValue;
     int8_t int8Value;
@@ -72,43 +138,381 @@ typedef union {
     int64_t timestampMillisecondValue;
     int64_t timestampMicrosecondValue;
     int64_t timestampNanosecondValue;
+    // Days since UNIX epoch.
+    int32_t dateValue;
+    // Microseconds since UNIX epoch.
+    int64_t datetimeValue;
+    // Time of day since midnight, in the unit of the column type.
+    int64_t timeSecondValue;
+    int64_t timeMillisecondValue;
+    int64_t timeMicrosecondValue;
+    int64_t timeNanosecondValue;
+    // Number of months.
+    int32_t intervalYearMonthValue;
+    // Days in the high 32 bits and milliseconds in the low 32 bits.
+    int64_t intervalDayTimeValue;
+    IntervalMonthDayNanoValue intervalMonthDayNanoValue;
     float float32Value;
     double doubleValue;
     BinaryValue binaryValue;
     char* stringValue;
+    Decimal128Value decimal128Value;
+    // NUL-terminated JSON text, add_row returns InvalidArgument if it is malformed.
+    char* jsonValue;
+    // `len` must be equal to the dimension of the column.
+    VectorValue vectorValue;
 } Value;
 
+typedef struct {
+    // Bitwise OR of ColumnIndex flags, 0 means no index.
+    uint32_t indexes;
+    // Analyzer of fulltext index, see FulltextAnalyzer.
+    int32_t fulltextAnalyzer;
+    bool fulltextCaseSensitive;
+    // Rows per granule of skipping index, 0 means the default 10240.
+    uint32_t skippingGranularity;
+} ColumnIndexOptions;
+
 typedef struct {
     char* name;
     int32_t dataType;
     int32_t semanticType;
+    // Precision (1 to 38) and scale (0 to precision) of Decimal128 columns.
+    int32_t precision;
+    int32_t scale;
+    // Dimension of Vector columns.
+    uint32_t dimension;
+    // Indexes of the column, fulltext index is only allowed on String columns.
+    ColumnIndexOptions indexOptions;
 } ColumnDef;
 
+// TLS options of connections to endpoints, paths are of PEM files.
+typedef struct {
+    // CA certificates the server certificate must be issued by, required.
+    const char* caCertPath;
+    // Client certificate and key for mutual TLS, either both or neither of them are set.
+    const char* clientCertPath;
+    const char* clientKeyPath;
+    // Name the server certificate must be valid for, NULL means the host of the endpoint.
+    const char* serverName;
+} TlsOptions;
+
+enum LogFormat {
+    LogFormatText = 0,
+    // Bunyan style JSON lines.
+    LogFormatJson = 1,
+};
+
+enum LogRotation {
+    LogRotationHourly = 0,
+    LogRotationDaily = 1,
+    LogRotationMinutely = 2,
+    LogRotationNever = 3,
+};
+
+// Options of init_logging, a zero-initialized struct keeps all defaults.
+typedef struct {
+    // Directory of log files, NULL means /tmp/greptimedb-client/logs.
+    const char* dir;
+    // Level directives like "info" or "warn,greptime=debug". NULL means the GT_LOG_LEVEL
+    // environment variable, or info if it is not set.
+    const char* level;
+    // LogFormat of both files and stdout.
+    int32_t format;
+    // LogRotation of log files.
+    int32_t rotation;
+    // Writes no log files, dir is not created then.
+    bool disableFile;
+    // Writes no logs to stdout.
+    bool disableStdout;
+} LoggingOptions;
+
+// Retry policy of requests failing with transient errors, such as an unavailable server, a busy
+// region, rate limiting or an exceeded deadline. Errors like schema conflicts are never retried.
+// A request that timed out may still have been applied by the server, so a retried write may be
+// applied twice, which duplicates rows in append-mode tables.
+typedef struct {
+    // Maximum number of attempts including the first one, 0 and 1 disable retries.
+    uint32_t maxAttempts;
+    // Backoff before the first retry, doubled by every following retry.
+    uint64_t baseBackoffMs;
+    // Upper bound of the backoff.
+    uint64_t maxBackoffMs;
+    // Randomizes every backoff into its upper half to spread retries of many clients.
+    bool jitter;
+} RetryOptions;
+
+// Flush thresholds of batch writers, 0 disables the corresponding trigger. Rows of a flush
+// that fails after the retries of the client's retry policy are discarded, not buffered again.
+typedef struct {
+    // Flushes once this number of rows are buffered.
+    size_t maxRows;
+    // Flushes once buffered rows reach this encoded size in bytes.
+    size_t maxBytes;
+    // Flushes buffered rows periodically.
+    uint64_t flushIntervalMs;
+} BatchWriterOptions;
+
+// Options of bulk writers, 0 means the default value.
+typedef struct {
+    // Maximum number of rows in a record batch, defaults to 8192.
+    size_t batchSize;
+    // Maximum number of record batches in flight, defaults to 4.
+    size_t parallelism;
+    // One of Compression, defaults to NoCompression.
+    int32_t compression;
+    // Timeout of every record batch, defaults to 60 seconds.
+    uint64_t timeoutMs;
+} BulkWriterOptions;
+
+// Values of a column. `values` points to an array with one element per row whose type
+// depends on the data type of the column:
+//   Boolean: bool; IntN/UintN: intN_t/uintN_t; Float32/Float64: float/double; Date: int32_t;
+//   Datetime, Timestamp* and Time*: int64_t; String and Json: char*; Binary: BinaryValue;
+//   Decimal128: Decimal128Value; IntervalYearMonth: int32_t; IntervalDayTime: int64_t;
+//   IntervalMonthDayNano: IntervalMonthDayNanoValue; Vector: VectorValue.
+// Interval and Vector columns are not supported by bulk writers.
+// Bit `i` (LSB first) of the optional `nulls` bitmap marks the i-th value as NULL, the
+// value itself is ignored. Only field columns can be NULL.
+typedef struct {
+    const void* values;
+    const uint8_t* nulls;
+} ColumnData;
+
+// Callback of async writes. `status` is Ok and `affected_rows` is the number of rows
+// acknowledged by the server if the write succeeded. If the client is freed before
+// the write completes, it is called with IllegalState. On failures, last_error_message
+// describes the failure inside the callback.
+typedef void (*write_callback_t)(void* user_data, int32_t status, uint32_t affected_rows);
+
 // Opaque Rust structs
 typedef struct RowBuilder row_builder_t;
 typedef struct Client client_t;
+typedef struct ClientOptions client_options_t;
+typedef struct BatchWriter batch_writer_t;
+typedef struct StreamInserter stream_inserter_t;
+typedef struct BulkWriter bulk_writer_t;
 typedef row_builder_t* p_row_builder_t;
 typedef client_t* p_client_t;
+typedef client_options_t* p_client_options_t;
+typedef batch_writer_t* p_batch_writer_t;
+typedef stream_inserter_t* p_stream_inserter_t;
+typedef bulk_writer_t* p_bulk_writer_t;
 
 // FFI functions
 
+// Initializes logging of the library, which must be called before the first client is
+// created, otherwise that client initializes logging with default options and this returns
+// IllegalState. It installs the global tracing subscriber of the process, and returns
+// IllegalState if the host application has installed one. With both file and stdout disabled,
+// nothing is installed, so logs go to the subscriber of the host application if it has one.
+extern int32_t init_logging(const LoggingOptions* options);
+
+// Creates options of a client to the database, see the client_options_* functions.
+extern int32_t new_client_options(char* database_name, p_client_options_t* options);
+
+// Adds an endpoint to connect to, at least one is required. Requests are spread across
+// endpoints in round robin order. A request failing with ServerUnavailable is retried on
+// the next endpoint, and the failed endpoint is only tried after others for 30 seconds.
+// Streams and bulk writers stick to the endpoint they are opened on.
+extern int32_t client_options_add_endpoint(p_client_options_t options, char* endpoint);
+
+// Sets basic auth credentials, same as the arguments of new_client.
+extern int32_t client_options_set_auth(p_client_options_t options, char* username, char* password);
+
+// Connects with TLS, see TlsOptions. Bulk writers only support mutual TLS without
+// serverName, new_bulk_writer returns InvalidArgument otherwise.
+extern int32_t client_options_set_tls(p_client_options_t options, TlsOptions* tls);
+
+// Sets timeouts of connecting to an endpoint and of every request, 0 keeps the default
+// of 1 second and 10 seconds. A request not completed in time, including its retries on
+// other endpoints, fails with Timeout. Failing to connect in time is reported as
+// ServerUnavailable, so that the next endpoint is tried. Streams are not bounded by the
+// request timeout.
+extern int32_t client_options_set_timeouts(p_client_options_t options, uint64_t connect_timeout_ms,
+                                           uint64_t timeout_ms);
+
+// Sets compression of gRPC messages, NoCompression by default. Only NoCompression and Zstd
+// are supported, otherwise it returns InvalidArgument.
+extern int32_t client_options_set_compression(p_client_options_t options, int32_t compression);
+
+// Sets the number of worker threads of the client, 0 means the number of CPUs.
+extern int32_t client_options_set_runtime_threads(p_client_options_t options, size_t threads);
+
+// Sets the retry policy of writes and deletes, requests are sent once by default. Retries
+// are bounded by the request timeout as well.
+extern int32_t client_options_set_retry(p_client_options_t options, const RetryOptions* retry);
+
+// Destroys client options, clients created from them are not affected.
+extern int32_t free_client_options(p_client_options_t* options);
+
+// Creates a new greptimedb client with given options, which can be freed once it returns.
+// The return value will be set to client pointer iff returned status code is Ok.
+extern int32_t new_client_with_options(p_client_options_t options, p_client_t* client);
+
 // Creates a new greptimedb client with given database name, endpoint and
-// basic auth credentials.
+// basic auth credentials. It's a shortcut of new_client_with_options.
 // `username` and `password` are optional and can both be NULL.
 // If `username` is non-NULL and `password` is NULL, empty password is used.
 // If `username` is NULL and `password` is non-NULL, it returns InvalidArgument.
 // The return value will be set to client pointer iff returned status code is Ok.
 extern int32_t new_client(char* database_name, char* endpoint, char* username, char* password, p_client_t* client);
 
+// Same as new_client, but connects with TLS. Unreadable files or a client certificate
+// without key make it return InvalidArgument.
+extern int32_t new_tls_client(char* database_name, char* endpoint, char* username, char* password,
+                              TlsOptions* tls, p_client_t* client);
+
 // Destroys greptimedb client and releases all underlying resources.
 extern int32_t free_client(p_client_t* client);
 
+// Every failing function records its failure for the calling thread, which is kept until the
+// next failure or clear_last_error on that thread, successful calls do not clear it.
+
+// Returns the status code of the last failure, or Ok if there is none.
+extern int32_t last_error_code(void);
+
+// Returns the status code sent by GreptimeDB with the last failure, or 0 if there is none.
+extern uint32_t last_error_server_code(void);
+
+// Returns the message of the last failure, or NULL if there is none. The string is owned by
+// the library and valid until the next failure or clear_last_error on the calling thread.
+extern const char* last_error_message(void);
+
+// Clears the last failure of the calling thread.
+extern void clear_last_error(void);
+
 // Inserts a new row to row builder.
 extern int32_t add_row(p_row_builder_t row_builder, Value* values, size_t len);
 
+// Inserts a new row to row builder, `nulls[i]` marks `values[i]` as NULL.
+// Both arrays must have `len` elements. Only field columns can be NULL,
+// otherwise it returns InvalidArgument.
+extern int32_t add_nullable_row(p_row_builder_t row_builder, Value* values, bool* nulls, size_t len);
+
+// Appends `rows` rows to row builder given one array per column, see ColumnData.
+// `column_len` must equal the number of defined columns. No row is appended if it
+// returns an error.
+extern int32_t add_columns(p_row_builder_t row_builder, ColumnData* columns, size_t column_len, size_t rows);
+
+// Sets a hint sent with every write of the row builder, setting an existing key replaces
+// its value. Hints like `ttl` (e.g. "7d"), `append_mode` ("true"), `merge_mode`
+// ("last_row" or "last_non_null") and `skip_wal` ("true") are applied when the
+// table is auto-created. Keys and values must be non-empty printable ASCII, and cannot
+// contain spaces, ',' or '='.
+extern int32_t set_hint(p_row_builder_t row_builder, char* key, char* value);
+
 // Writes a row of data inside row builder to database.
 extern int32_t write_row(p_client_t client, p_row_builder_t row);
 
+// Writes a row of data inside row builder to database, `affected_rows` will be set to
+// the number of rows acknowledged by the server iff returned status code is Ok.
+extern int32_t write_row_with_affected_rows(p_client_t client, p_row_builder_t row, uint32_t* affected_rows);
+
+// Writes rows of all `len` row builders to database in a single request. Every row
+// builder must be distinct. Hints are sent once for the whole request, so all row builders
+// must have the same hints, otherwise it returns InvalidArgument without writing anything.
+// `affected_rows` is optional, if non-NULL it will be set to the number of rows
+// acknowledged by the server iff returned status code is Ok.
+extern int32_t write_rows(p_client_t client, p_row_builder_t* rows, size_t len, uint32_t* affected_rows);
+
+// Same as write_rows, but fails with Timeout if the write does not complete within `timeout_ms`
+// instead of the request timeout of the client. A `timeout_ms` of 0 means the request timeout
+// of the client.
+extern int32_t write_rows_with_timeout(p_client_t client, p_row_builder_t* rows, size_t len, uint64_t timeout_ms,
+                                       uint32_t* affected_rows);
+
+// Same as write_rows, but `attempts` is set to the number of attempts made by the retry
+// policy of the client, including on failures. Both out-parameters are optional.
+extern int32_t write_rows_with_attempts(p_client_t client, p_row_builder_t* rows, size_t len, uint32_t* affected_rows,
+                                        uint32_t* attempts);
+
+// Deletes rows identified by rows inside all `len` row builders in a single request. Row
+// builders must only define tag and timestamp columns, otherwise it returns InvalidArgument
+// without deleting anything. Hints are ignored. `affected_rows` is optional, if non-NULL it
+// will be set to the number of rows deleted by the server iff returned status code is Ok.
+extern int32_t delete_rows(p_client_t client, p_row_builder_t* rows, size_t len, uint32_t* affected_rows);
+
+// Same as delete_rows, but fails with Timeout if the delete does not complete within `timeout_ms`
+// instead of the request timeout of the client. A `timeout_ms` of 0 means the request timeout
+// of the client.
+extern int32_t delete_rows_with_timeout(p_client_t client, p_row_builder_t* rows, size_t len, uint64_t timeout_ms,
+                                        uint32_t* affected_rows);
+
+// Sends rows of data inside row builder to database in background and returns immediately,
+// the row builder can be reused once it returns. `callback` is called exactly once with
+// `user_data` on a client thread iff returned status code is Ok. It must not block or call
+// other functions of the same client.
+extern int32_t write_row_async(p_client_t client, p_row_builder_t row, write_callback_t callback, void* user_data);
+
+// Same as write_rows, but sends the request in background like write_row_async.
+extern int32_t write_rows_async(p_client_t client, p_row_builder_t* rows, size_t len, write_callback_t callback,
+                                void* user_data);
+
+// Creates a batch writer which buffers rows and writes them from a background task
+// once any threshold in `options` is reached. `callback` is optional, if non-NULL it is
+// called with `user_data` on a client thread after every background flush, see
+// write_callback_t. A flush takes all buffered rows, if it fails they are discarded and the
+// callback receives the failure, so the application must write them again if it needs them.
+// The batch writer must be freed before the client.
+extern int32_t new_batch_writer(p_client_t client, BatchWriterOptions* options, write_callback_t callback,
+                                void* user_data, p_batch_writer_t* writer);
+
+// Moves rows of data inside row builder to the batch writer, the row builder can be reused
+// once it returns. It's safe to call it from multiple threads. Rows are grouped by the hints
+// of their row builder, and a flush writes each group in its own request with its own hints,
+// so hints of one table never apply to another one. Hints of a row builder without rows are
+// ignored.
+extern int32_t batch_writer_write(p_batch_writer_t writer, p_row_builder_t row);
+
+// Writes all buffered rows to database. `affected_rows` is optional, if non-NULL it will be set
+// to the number of rows acknowledged by the server iff returned status code is Ok. The rows are
+// discarded if it fails.
+extern int32_t batch_writer_flush(p_batch_writer_t writer, uint32_t* affected_rows);
+
+// Writes buffered rows, then destroys the batch writer and releases all underlying resources.
+// It must not be called from a callback.
+extern int32_t free_batch_writer(p_batch_writer_t* writer);
+
+// Opens a stream which sends writes over a single long-lived gRPC stream. A stream
+// must be used by one thread at a time and freed before the client.
+extern int32_t new_stream_inserter(p_client_t client, p_stream_inserter_t* stream);
+
+// Moves rows of data inside row builder to the stream, the row builder can be reused
+// once it returns. It blocks only if the stream is congested. Row builders with hints
+// are rejected with InvalidArgument.
+extern int32_t stream_write(p_stream_inserter_t stream, p_row_builder_t row);
+
+// Closes the stream and waits for the server to acknowledge all writes. `affected_rows`
+// is optional, if non-NULL it will be set to the total number of rows acknowledged by
+// the server iff returned status code is Ok. The stream cannot be written after it.
+extern int32_t stream_finish(p_stream_inserter_t stream, uint32_t* affected_rows);
+
+// Destroys the stream and releases all underlying resources. Rows of an unfinished
+// stream are sent, but their result is not reported.
+extern int32_t free_stream_inserter(p_stream_inserter_t* stream);
+
+// Creates a bulk writer which sends columnar data over Arrow Flight to the table and
+// columns defined in row builder `schema`, rows inside `schema` are not written. The
+// table must exist. A bulk writer must be used by one thread at a time and freed
+// before the client.
+extern int32_t new_bulk_writer(p_client_t client, p_row_builder_t schema, BulkWriterOptions* options,
+                               p_bulk_writer_t* writer);
+
+// Sends `rows` rows of `column_len` columns, which must match the columns of the schema
+// in order. Columns are copied into record batches of at most `batchSize` rows, so they
+// can be reused once it returns. It blocks only if too many batches are in flight.
+extern int32_t bulk_writer_write(p_bulk_writer_t writer, ColumnData* columns, size_t column_len, size_t rows);
+
+// Waits for the server to acknowledge all record batches. `affected_rows` is optional, if
+// non-NULL it will be set to the total number of rows acknowledged by the server iff
+// returned status code is Ok. The writer cannot be written after it.
+extern int32_t bulk_writer_finish(p_bulk_writer_t writer, uint64_t* affected_rows);
+
+// Destroys the bulk writer and releases all underlying resources. Record batches not yet
+// acknowledged when it's called without bulk_writer_finish may be lost.
+extern int32_t free_bulk_writer(p_bulk_writer_t* writer);
+
 // Creates a new row value builder. This is a internal function,
 // use create_row_builder instead to create a row builder.
 extern int32_t _new_row_builder(char* table_name, p_row_builder_t* res);
@@ -116,6 +520,18 @@ extern int32_t _new_row_builder(char* table_name, p_row_builder_t* res);
 // Defines columns to row builder.
 extern int32_t _define_column(p_row_builder_t row_builder, char* name, int32_t data_type, int32_t semantic_type);
 
+// Defines a Decimal128 column with given precision and scale to row builder.
+// Values exceeding the precision are rejected by add_row with InvalidArgument.
+extern int32_t _define_decimal128_column(p_row_builder_t row_builder, char* name, int32_t semantic_type,
+                                         int32_t precision, int32_t scale);
+
+// Defines a Vector column with given dimension to row builder.
+extern int32_t _define_vector_column(p_row_builder_t row_builder, char* name, int32_t semantic_type, uint32_t dim);
+
+// Sets index options of a defined column. Options only take effect when the
+// table is auto-created by the first write.
+extern int32_t _set_column_index_options(p_row_builder_t row_builder, char* name, ColumnIndexOptions* options);
+
 // Destroys row builder and releases all underlying resource.
 int32_t free_row_builder(row_builder_t** res);
 
@@ -128,7 +544,21 @@ static inline int32_t new_row_builder(char* table_name, ColumnDef columns[], siz
     }
     size_t i;
     for (i = 0; i < len; i++) {
-        int code = _define_column(p_builder, columns[i].name, columns[i].dataType, columns[i].semanticType);
+        int code;
+        switch (columns[i].dataType) {
+            case Decimal128:
+                code = _define_decimal128_column(p_builder, columns[i].name, columns[i].semanticType,
+                                                 columns[i].precision, columns[i].scale);
+                break;
+            case Vector:
+                code = _define_vector_column(p_builder, columns[i].name, columns[i].semanticType, columns[i].dimension);
+                break;
+            default:
+                code = _define_column(p_builder, columns[i].name, columns[i].dataType, columns[i].semanticType);
+        }
+        if (code == Ok && columns[i].indexOptions.indexes != 0) {
+            code = _set_column_index_options(p_builder, columns[i].name, &columns[i].indexOptions);
+        }
         if (code != Ok) {
             free_row_builder(&p_builder);
             return code;
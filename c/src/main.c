@@ -83,9 +83,11 @@ int main() {
 
     add_row(builder, values_shanghai, sizeof(values_shanghai) / sizeof(values_shanghai[0]));
 
-    // 6. write row to database
-    err_code = write_row(client, builder);
+    // 6. write row to database and get the number of affected rows
+    uint32_t affected_rows = 0;
+    err_code = write_row_with_affected_rows(client, builder, &affected_rows);
     assert(err_code == 0);
+    assert(affected_rows == 1);
 
     // 7. destroy row builder and client.
     err_code = free_row_builder(&builder);
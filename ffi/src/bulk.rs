@@ -0,0 +1,288 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::column::{ColumnData, to_arrow_arrays};
+use crate::row::RowBuilder;
+use crate::{Client, error};
+use greptimedb_ingester::api::v1::column_data_type_extension::TypeExt;
+use greptimedb_ingester::api::v1::{ColumnDataType, ColumnDataTypeExtension, ColumnSchema};
+use greptimedb_ingester::arrow_array::RecordBatch;
+use greptimedb_ingester::arrow_schema::SchemaRef;
+use greptimedb_ingester::{
+    BulkStreamWriter, BulkWriteOptions, Column, CompressionType, DataTypeExtension, Rows,
+    SemanticType, TableSchema,
+};
+use snafu::{OptionExt, ResultExt, ensure};
+use tokio::runtime::Handle;
+use tracing::{debug, info};
+
+const DEFAULT_BATCH_SIZE: usize = 8192;
+
+/// Options of [BulkWriter], 0 means the default value.
+#[repr(C)]
+pub struct BulkWriterOptions {
+    batch_size: usize,
+    parallelism: usize,
+    compression: i32,
+    timeout_ms: u64,
+}
+
+impl BulkWriterOptions {
+    fn to_write_options(&self) -> error::Result<BulkWriteOptions> {
+        let compression = match self.compression {
+            0 => CompressionType::None,
+            1 => CompressionType::Lz4,
+            2 => CompressionType::Zstd,
+            _ => {
+                return error::InvalidCompressionSnafu {
+                    compression: self.compression,
+                }
+                .fail();
+            }
+        };
+        let mut options = BulkWriteOptions::default().with_compression(compression);
+        if self.parallelism > 0 {
+            options = options.with_parallelism(self.parallelism);
+        }
+        if self.timeout_ms > 0 {
+            options = options.with_timeout(Duration::from_millis(self.timeout_ms));
+        }
+        Ok(options)
+    }
+}
+
+/// Writes columnar data of a table as Arrow record batches over Arrow Flight.
+pub struct BulkWriter {
+    runtime: Handle,
+    writer: Option<BulkStreamWriter>,
+    schema: Vec<ColumnSchema>,
+    arrow_schema: SchemaRef,
+    batch_size: usize,
+    affected_rows: u64,
+}
+
+impl BulkWriter {
+    /// Creates a writer of the table and columns defined in `template`.
+    pub fn new(
+        client: &Client,
+        template: &RowBuilder,
+        options: &BulkWriterOptions,
+    ) -> error::Result<Self> {
+        let table_schema = to_table_schema(template)?;
+        let write_options = options.to_write_options()?;
+        ensure!(
+            client.endpoints.supports_bulk(),
+            error::UnsupportedBulkTlsSnafu
+        );
+        let writer = client
+            .runtime
+            .block_on(client.endpoints.call(write_options, |endpoint, options| {
+                // safety: all endpoints have a database if bulk is supported.
+                let database = endpoint.database.as_ref().unwrap();
+                BulkStreamWriter::new(database, &table_schema, options)
+            }))
+            .map_err(Box::new)
+            .context(error::InsertReqSnafu)?;
+        let arrow_schema = writer
+            .alloc_rows_buffer(0, 0)
+            .map_err(Box::new)
+            .context(error::InsertReqSnafu)?
+            .schema()
+            .clone();
+        let batch_size = match options.batch_size {
+            0 => DEFAULT_BATCH_SIZE,
+            n => n,
+        };
+
+        Ok(Self {
+            runtime: client.runtime.handle().clone(),
+            writer: Some(writer),
+            schema: template.schema().to_vec(),
+            arrow_schema: Arc::new(arrow_schema),
+            batch_size,
+            affected_rows: 0,
+        })
+    }
+
+    /// Encodes `rows` rows of columns into record batches of at most batch size
+    /// rows and sends them. It blocks only if too many batches are in flight.
+    pub unsafe fn write(&mut self, columns: &[ColumnData], rows: usize) -> error::Result<()> {
+        let writer = self.writer.as_mut().context(error::StreamClosedSnafu)?;
+        let arrays =
+            unsafe { to_arrow_arrays(&self.schema, self.arrow_schema.fields(), columns, rows)? };
+        let batch = RecordBatch::try_new(self.arrow_schema.clone(), arrays)
+            .context(error::CreateRecordBatchSnafu)?;
+
+        let mut offset = 0;
+        while offset < rows {
+            let len = self.batch_size.min(rows - offset);
+            let batch = Rows::from_record_batch(batch.slice(offset, len))
+                .map_err(Box::new)
+                .context(error::InsertReqSnafu)?;
+            self.runtime
+                .block_on(writer.write_rows_async(batch))
+                .map_err(Box::new)
+                .context(error::InsertReqSnafu)?;
+            offset += len;
+        }
+
+        self.affected_rows += writer
+            .flush_completed_responses()
+            .iter()
+            .map(|r| r.affected_rows() as u64)
+            .sum::<u64>();
+        Ok(())
+    }
+
+    /// Waits for all batches to be acknowledged and returns the number of rows
+    /// affected by all writes.
+    pub fn finish(&mut self) -> error::Result<u64> {
+        let writer = self.writer.take().context(error::StreamClosedSnafu)?;
+        let responses = self
+            .runtime
+            .block_on(writer.finish_with_responses())
+            .map_err(Box::new)
+            .context(error::InsertReqSnafu)?;
+        let affected_rows = self.affected_rows
+            + responses
+                .iter()
+                .map(|r| r.affected_rows() as u64)
+                .sum::<u64>();
+        debug!("Bulk write finished, affected rows: {}", affected_rows);
+        Ok(affected_rows)
+    }
+}
+
+impl Drop for BulkWriter {
+    fn drop(&mut self) {
+        info!("Dropping bulk writer");
+    }
+}
+
+fn to_table_schema(template: &RowBuilder) -> error::Result<TableSchema> {
+    let mut columns = Vec::with_capacity(template.schema().len());
+    for col in template.schema() {
+        // safety: we've checked the validity of data type and semantic type value in [add_column].
+        let data_type = ColumnDataType::try_from(col.datatype).unwrap();
+        let semantic_type = SemanticType::try_from(col.semantic_type).unwrap();
+        let data_type_extension = match &col.datatype_extension {
+            Some(ColumnDataTypeExtension {
+                type_ext: Some(TypeExt::DecimalType(ext)),
+            }) => Some(DataTypeExtension::Decimal128 {
+                // safety: precision and scale are checked in [add_decimal128_col].
+                precision: ext.precision as u8,
+                scale: ext.scale as i8,
+            }),
+            _ => None,
+        };
+        columns.push(Column {
+            name: col.column_name.clone(),
+            data_type,
+            semantic_type,
+            data_type_extension,
+        });
+    }
+
+    Ok(TableSchema::builder()
+        .name(template.table_name())
+        .columns(columns)
+        .build()
+        // safety: all fields are set.
+        .unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ErrorExt, StatusCode};
+    use crate::test_util;
+
+    fn options(batch_size: usize, parallelism: usize, compression: i32) -> BulkWriterOptions {
+        BulkWriterOptions {
+            batch_size,
+            parallelism,
+            compression,
+            timeout_ms: 0,
+        }
+    }
+
+    fn template() -> RowBuilder {
+        let mut builder = RowBuilder::new("demo".to_string());
+        builder
+            .add_col(
+                "ts".to_string(),
+                ColumnDataType::TimestampMillisecond as i32,
+                SemanticType::Timestamp as i32,
+            )
+            .unwrap();
+        builder
+            .add_col(
+                "v".to_string(),
+                ColumnDataType::Int64 as i32,
+                SemanticType::Field as i32,
+            )
+            .unwrap();
+        builder
+    }
+
+    #[test]
+    fn to_write_options_rejects_unknown_compression() {
+        for compression in 0..=2 {
+            assert!(options(0, 0, compression).to_write_options().is_ok());
+        }
+        let err = options(0, 0, 3).to_write_options().unwrap_err();
+        assert!(matches!(
+            err,
+            error::Error::InvalidCompression { compression: 3, .. }
+        ));
+        assert_eq!(err.status_code(), StatusCode::InvalidArgument);
+    }
+
+    #[test]
+    fn bulk_writer_slices_rows_into_batches() {
+        let server = test_util::StubServer::start(Default::default());
+        let client = test_util::new_client(&server.addr());
+
+        for parallelism in [1, 4] {
+            let before = server.batches();
+            let mut writer =
+                BulkWriter::new(&client, &template(), &options(2, parallelism, 0)).unwrap();
+            let ts: Vec<i64> = (0..5).collect();
+            let v: Vec<i64> = (0..5).map(|i| i * 10).collect();
+            let columns = [
+                ColumnData {
+                    values: ts.as_ptr() as _,
+                    nulls: std::ptr::null(),
+                },
+                ColumnData {
+                    values: v.as_ptr() as _,
+                    nulls: std::ptr::null(),
+                },
+            ];
+            unsafe { writer.write(&columns, 5).unwrap() };
+            unsafe { writer.write(&columns, 1).unwrap() };
+
+            assert_eq!(writer.finish().unwrap(), 6);
+            // 5 rows are sliced into batches of 2, 2 and 1 row.
+            assert_eq!(server.batches() - before, 4);
+            assert!(matches!(
+                writer.finish(),
+                Err(error::Error::StreamClosed { .. })
+            ));
+        }
+    }
+}
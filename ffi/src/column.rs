@@ -0,0 +1,381 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::mem::ManuallyDrop;
+use std::sync::Arc;
+
+use crate::error;
+use crate::row::{
+    BinaryValue, Decimal128Value, IntervalMonthDayNanoValue, Value, VectorValue,
+    check_decimal128_precision,
+};
+use crate::util::{convert_c_binary, convert_c_string};
+use greptimedb_ingester::SemanticType;
+use greptimedb_ingester::api::v1::{ColumnDataType, ColumnSchema};
+use greptimedb_ingester::arrow_array::{
+    ArrayRef, BinaryArray, BooleanArray, Date32Array, Decimal128Array, Float32Array, Float64Array,
+    Int8Array, Int16Array, Int32Array, Int64Array, StringArray, Time32MillisecondArray,
+    Time32SecondArray, Time64MicrosecondArray, Time64NanosecondArray, TimestampMicrosecondArray,
+    TimestampMillisecondArray, TimestampNanosecondArray, TimestampSecondArray, UInt8Array,
+    UInt16Array, UInt32Array, UInt64Array,
+};
+use greptimedb_ingester::arrow_schema::{DataType, Field};
+use snafu::{OptionExt, ResultExt, ensure};
+
+/// Values of a column. `values` points to an array of `rows` elements whose
+/// type depends on the column data type, and bit `i` (LSB first) of the
+/// optional `nulls` bitmap marks the i-th value as NULL.
+#[repr(C)]
+pub struct ColumnData {
+    pub(crate) values: *const libc::c_void,
+    pub(crate) nulls: *const u8,
+}
+
+impl ColumnData {
+    pub(crate) fn is_null(&self, row: usize) -> bool {
+        !self.nulls.is_null() && unsafe { *self.nulls.add(row / 8) } & (1 << (row % 8)) != 0
+    }
+
+    unsafe fn values<T>(&self, rows: usize) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.values as *const T, rows) }
+    }
+
+    /// Reads the value of a row into the [Value] field of the column data type.
+    pub(crate) unsafe fn value(&self, col: &ColumnSchema, row: usize) -> error::Result<Value> {
+        macro_rules! read {
+            ($field: ident, $ty: ty) => {
+                Value {
+                    $field: unsafe { *(self.values as *const $ty).add(row) },
+                }
+            };
+        }
+
+        // safety: we've checked the validity of data type value in [add_column].
+        let data_type = ColumnDataType::try_from(col.datatype).unwrap();
+        let value = match data_type {
+            ColumnDataType::Boolean => read!(bool_value, libc::c_char),
+            ColumnDataType::Int8 => read!(i8_value, libc::c_schar),
+            ColumnDataType::Int16 => read!(i16_value, libc::c_short),
+            ColumnDataType::Int32 => read!(i32_value, libc::c_int),
+            ColumnDataType::Int64 => read!(i64_value, libc::c_long),
+            ColumnDataType::Uint8 => read!(u8_value, libc::c_uchar),
+            ColumnDataType::Uint16 => read!(u16_value, libc::c_ushort),
+            ColumnDataType::Uint32 => read!(u32_value, libc::c_uint),
+            ColumnDataType::Uint64 => read!(u64_value, libc::c_ulong),
+            ColumnDataType::Float32 => read!(f32_value, libc::c_float),
+            ColumnDataType::Float64 => read!(f64_value, libc::c_double),
+            ColumnDataType::TimestampSecond => read!(timestamp_second_value, libc::c_long),
+            ColumnDataType::TimestampMillisecond => {
+                read!(timestamp_millisecond_value, libc::c_long)
+            }
+            ColumnDataType::TimestampMicrosecond => {
+                read!(timestamp_microsecond_value, libc::c_long)
+            }
+            ColumnDataType::TimestampNanosecond => read!(timestamp_nanosecond_value, libc::c_long),
+            ColumnDataType::Date => read!(date_value, libc::c_int),
+            ColumnDataType::Datetime => read!(datetime_value, libc::c_long),
+            ColumnDataType::TimeSecond => read!(time_second_value, libc::c_long),
+            ColumnDataType::TimeMillisecond => read!(time_millisecond_value, libc::c_long),
+            ColumnDataType::TimeMicrosecond => read!(time_microsecond_value, libc::c_long),
+            ColumnDataType::TimeNanosecond => read!(time_nanosecond_value, libc::c_long),
+            ColumnDataType::IntervalYearMonth => read!(interval_year_month_value, libc::c_int),
+            ColumnDataType::IntervalDayTime => read!(interval_day_time_value, libc::c_long),
+            ColumnDataType::IntervalMonthDayNano => {
+                read!(interval_month_day_nano_value, IntervalMonthDayNanoValue)
+            }
+            ColumnDataType::Binary => Value {
+                binary_value: ManuallyDrop::new(unsafe {
+                    std::ptr::read((self.values as *const BinaryValue).add(row))
+                }),
+            },
+            ColumnDataType::String => read!(string_value, *const libc::c_char),
+            ColumnDataType::Decimal128 => read!(decimal128_value, Decimal128Value),
+            ColumnDataType::Json => read!(json_value, *const libc::c_char),
+            ColumnDataType::Vector => read!(vector_value, VectorValue),
+            _ => {
+                return error::UnsupportedDataTypeSnafu {
+                    data_type: col.datatype,
+                }
+                .fail();
+            }
+        };
+        Ok(value)
+    }
+
+    /// Maps non-null values of the column, null values are left as `None`.
+    unsafe fn map<T, R>(
+        &self,
+        rows: usize,
+        f: impl Fn(&T) -> error::Result<R>,
+    ) -> error::Result<Vec<Option<R>>> {
+        unsafe { self.values::<T>(rows) }
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (!self.is_null(i)).then(|| f(v)).transpose())
+            .collect()
+    }
+}
+
+/// Checks columns against the schema, only field columns can contain nulls.
+pub(crate) fn check_columns(
+    schema: &[ColumnSchema],
+    columns: &[ColumnData],
+    rows: usize,
+) -> error::Result<()> {
+    ensure!(
+        schema.len() == columns.len(),
+        error::SchemaMismatchSnafu {
+            value_len: columns.len(),
+            schema_len: schema.len(),
+        }
+    );
+    for (col, data) in schema.iter().zip(columns) {
+        ensure!(rows == 0 || !data.values.is_null(), error::NullPointerSnafu);
+        ensure!(
+            col.semantic_type == SemanticType::Field as i32 || !(0..rows).any(|i| data.is_null(i)),
+            error::NullValueSnafu {
+                column: &col.column_name,
+            }
+        );
+    }
+    Ok(())
+}
+
+macro_rules! primitive_array {
+    ($data: expr, $rows: expr, $native: ty, $array: ty) => {
+        Arc::new(
+            unsafe { $data.map::<$native, $native>($rows, |v| Ok(*v)) }?
+                .into_iter()
+                .collect::<$array>(),
+        )
+    };
+}
+
+/// Converts columns to Arrow arrays of the given fields.
+pub unsafe fn to_arrow_arrays(
+    schema: &[ColumnSchema],
+    fields: &[Arc<Field>],
+    columns: &[ColumnData],
+    rows: usize,
+) -> error::Result<Vec<ArrayRef>> {
+    check_columns(schema, columns, rows)?;
+
+    let mut arrays = Vec::with_capacity(columns.len());
+    for ((col, field), data) in schema.iter().zip(fields).zip(columns) {
+        // safety: we've checked the validity of data type value in [add_column].
+        let data_type = ColumnDataType::try_from(col.datatype).unwrap();
+        let array: ArrayRef = match data_type {
+            ColumnDataType::Boolean => Arc::new(BooleanArray::from(unsafe {
+                data.map::<u8, bool>(rows, |v| Ok(*v != 0))
+            }?)),
+            ColumnDataType::Int8 => primitive_array!(data, rows, i8, Int8Array),
+            ColumnDataType::Int16 => primitive_array!(data, rows, i16, Int16Array),
+            ColumnDataType::Int32 => primitive_array!(data, rows, i32, Int32Array),
+            ColumnDataType::Int64 => primitive_array!(data, rows, i64, Int64Array),
+            ColumnDataType::Uint8 => primitive_array!(data, rows, u8, UInt8Array),
+            ColumnDataType::Uint16 => primitive_array!(data, rows, u16, UInt16Array),
+            ColumnDataType::Uint32 => primitive_array!(data, rows, u32, UInt32Array),
+            ColumnDataType::Uint64 => primitive_array!(data, rows, u64, UInt64Array),
+            ColumnDataType::Float32 => primitive_array!(data, rows, f32, Float32Array),
+            ColumnDataType::Float64 => primitive_array!(data, rows, f64, Float64Array),
+            ColumnDataType::Date => primitive_array!(data, rows, i32, Date32Array),
+            ColumnDataType::TimestampSecond => {
+                primitive_array!(data, rows, i64, TimestampSecondArray)
+            }
+            ColumnDataType::TimestampMillisecond => {
+                primitive_array!(data, rows, i64, TimestampMillisecondArray)
+            }
+            ColumnDataType::Datetime | ColumnDataType::TimestampMicrosecond => {
+                primitive_array!(data, rows, i64, TimestampMicrosecondArray)
+            }
+            ColumnDataType::TimestampNanosecond => {
+                primitive_array!(data, rows, i64, TimestampNanosecondArray)
+            }
+            ColumnDataType::TimeSecond => Arc::new(Time32SecondArray::from(unsafe {
+                data.map::<i64, i32>(rows, |v| time32(col, *v))
+            }?)),
+            ColumnDataType::TimeMillisecond => Arc::new(Time32MillisecondArray::from(unsafe {
+                data.map::<i64, i32>(rows, |v| time32(col, *v))
+            }?)),
+            ColumnDataType::TimeMicrosecond => {
+                primitive_array!(data, rows, i64, Time64MicrosecondArray)
+            }
+            ColumnDataType::TimeNanosecond => {
+                primitive_array!(data, rows, i64, Time64NanosecondArray)
+            }
+            ColumnDataType::String => Arc::new(StringArray::from(unsafe {
+                data.map::<*const libc::c_char, String>(rows, |v| convert_c_string(*v))
+            }?)),
+            ColumnDataType::Binary => {
+                let values = unsafe {
+                    data.map::<BinaryValue, Vec<u8>>(rows, |v| convert_c_binary(v.data, v.len))
+                }?;
+                Arc::new(BinaryArray::from_iter(values))
+            }
+            ColumnDataType::Json => {
+                let values = unsafe {
+                    data.map::<*const libc::c_char, Vec<u8>>(rows, |v| {
+                        let json = convert_c_string(*v)?;
+                        serde_json::from_str::<serde::de::IgnoredAny>(&json).context(
+                            error::InvalidJsonSnafu {
+                                column: &col.column_name,
+                            },
+                        )?;
+                        Ok(json.into_bytes())
+                    })
+                }?;
+                Arc::new(BinaryArray::from_iter(values))
+            }
+            ColumnDataType::Decimal128 => {
+                let values = unsafe {
+                    data.map::<Decimal128Value, i128>(rows, |v| {
+                        check_decimal128_precision(col, v.hi, v.lo)?;
+                        Ok(((v.hi as i128) << 64) | (v.lo as u64 as i128))
+                    })
+                }?;
+                let array = Decimal128Array::from(values);
+                match field.data_type() {
+                    DataType::Decimal128(precision, scale) => Arc::new(
+                        array
+                            .with_precision_and_scale(*precision, *scale)
+                            .context(error::CreateRecordBatchSnafu)?,
+                    ),
+                    _ => Arc::new(array),
+                }
+            }
+            _ => {
+                return error::UnsupportedDataTypeSnafu {
+                    data_type: col.datatype,
+                }
+                .fail();
+            }
+        };
+        arrays.push(array);
+    }
+    Ok(arrays)
+}
+
+/// Arrow stores time of seconds and milliseconds in 32 bits.
+fn time32(col: &ColumnSchema, value: i64) -> error::Result<i32> {
+    i32::try_from(value)
+        .ok()
+        .context(error::ValueOutOfRangeSnafu {
+            column: &col.column_name,
+            value,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::row::RowBuilder;
+    use greptimedb_ingester::arrow_array::Array;
+
+    fn schema() -> (RowBuilder, Vec<Arc<Field>>) {
+        let mut builder = RowBuilder::new("demo".to_string());
+        builder
+            .add_col(
+                "host".to_string(),
+                ColumnDataType::String as i32,
+                SemanticType::Tag as i32,
+            )
+            .unwrap();
+        builder
+            .add_col(
+                "ts".to_string(),
+                ColumnDataType::TimestampMillisecond as i32,
+                SemanticType::Timestamp as i32,
+            )
+            .unwrap();
+        builder
+            .add_col(
+                "cpu".to_string(),
+                ColumnDataType::Float64 as i32,
+                SemanticType::Field as i32,
+            )
+            .unwrap();
+        let fields = vec![
+            Arc::new(Field::new("host", DataType::Utf8, true)),
+            Arc::new(Field::new(
+                "ts",
+                DataType::Timestamp(
+                    greptimedb_ingester::arrow_schema::TimeUnit::Millisecond,
+                    None,
+                ),
+                false,
+            )),
+            Arc::new(Field::new("cpu", DataType::Float64, true)),
+        ];
+        (builder, fields)
+    }
+
+    #[test]
+    fn to_arrow_arrays_converts_columns_with_nulls() {
+        let (builder, fields) = schema();
+        let hosts = [c"a".as_ptr(), c"b".as_ptr(), c"c".as_ptr()];
+        let ts = [1i64, 2, 3];
+        let cpu = [0.5f64, 0.0, 1.5];
+        let nulls = [0b010u8];
+        let columns = [
+            ColumnData {
+                values: hosts.as_ptr() as _,
+                nulls: std::ptr::null(),
+            },
+            ColumnData {
+                values: ts.as_ptr() as _,
+                nulls: std::ptr::null(),
+            },
+            ColumnData {
+                values: cpu.as_ptr() as _,
+                nulls: nulls.as_ptr(),
+            },
+        ];
+
+        let arrays = unsafe { to_arrow_arrays(builder.schema(), &fields, &columns, 3) }.unwrap();
+
+        assert_eq!(arrays.len(), 3);
+        let cpu = arrays[2].as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(cpu.len(), 3);
+        assert!(cpu.is_null(1));
+        assert_eq!(cpu.value(2), 1.5);
+        let hosts = arrays[0].as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(hosts.value(1), "b");
+    }
+
+    #[test]
+    fn to_arrow_arrays_rejects_null_tags() {
+        let (builder, fields) = schema();
+        let hosts = [c"a".as_ptr()];
+        let ts = [1i64];
+        let cpu = [0.5f64];
+        let nulls = [0b1u8];
+        let columns = [
+            ColumnData {
+                values: hosts.as_ptr() as _,
+                nulls: nulls.as_ptr(),
+            },
+            ColumnData {
+                values: ts.as_ptr() as _,
+                nulls: std::ptr::null(),
+            },
+            ColumnData {
+                values: cpu.as_ptr() as _,
+                nulls: std::ptr::null(),
+            },
+        ];
+
+        let err = unsafe { to_arrow_arrays(builder.schema(), &fields, &columns, 1) }.unwrap_err();
+        assert!(matches!(err, error::Error::NullValue { .. }));
+    }
+}
@@ -0,0 +1,293 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::error;
+use crate::error::is_unavailable;
+use crate::options::{ClientOptions, TlsConfig};
+use crate::retry::RetryPolicy;
+use greptimedb_ingester::api::v1::auth_header::AuthScheme;
+use greptimedb_ingester::api::v1::greptime_database_client::GreptimeDatabaseClient;
+use greptimedb_ingester::api::v1::{AuthHeader, RequestHeader};
+use greptimedb_ingester::database::Database;
+use greptimedb_ingester::{ChannelConfig, ChannelManager};
+use snafu::ResultExt;
+use tonic::codec::CompressionEncoding;
+use tonic::transport::{Channel, ClientTlsConfig};
+use tracing::warn;
+
+/// How long an endpoint which failed with unavailable is tried only after others.
+const EJECT_DURATION: Duration = Duration::from_secs(30);
+
+/// A GreptimeDB frontend node.
+pub struct Endpoint {
+    addr: String,
+    /// Client of the GreptimeDatabase service on the channel to this endpoint.
+    pub(crate) client: GreptimeDatabaseClient<Channel>,
+    /// Used by bulk writers, `None` if the TLS settings are not supported by
+    /// the ingester.
+    pub(crate) database: Option<Database>,
+    ejected_until: Mutex<Option<Instant>>,
+}
+
+impl Endpoint {
+    fn is_ejected(&self, now: Instant) -> bool {
+        self.ejected_until
+            .lock()
+            .unwrap()
+            .is_some_and(|until| until > now)
+    }
+
+    fn eject(&self) {
+        *self.ejected_until.lock().unwrap() = Some(Instant::now() + EJECT_DURATION);
+    }
+
+    fn recover(&self) {
+        *self.ejected_until.lock().unwrap() = None;
+    }
+}
+
+/// Spreads requests across endpoints in round robin order. An endpoint failing
+/// with unavailable is ejected for a while, and the request is retried on the
+/// next endpoint.
+#[derive(Clone)]
+pub struct Endpoints {
+    endpoints: Arc<[Endpoint]>,
+    /// Header of all requests, carrying the database and auth.
+    pub(crate) header: RequestHeader,
+    next: Arc<AtomicUsize>,
+    retry: RetryPolicy,
+}
+
+impl Endpoints {
+    pub fn new(options: &ClientOptions, auth: Option<AuthScheme>) -> error::Result<Self> {
+        let tls = options
+            .tls()
+            .map(TlsConfig::to_client_tls_config)
+            .transpose()?;
+        let mut endpoints = Vec::with_capacity(options.endpoints.len());
+        for addr in &options.endpoints {
+            let client = connect(addr, &options.channel_config(), tls.as_ref())?;
+            let database = match options.tls() {
+                Some(tls) if tls.to_client_tls_option().is_none() => None,
+                _ => Some(new_database(addr, options, auth.clone())?),
+            };
+            endpoints.push(Endpoint {
+                addr: addr.clone(),
+                client,
+                database,
+                ejected_until: Mutex::new(None),
+            });
+        }
+        let header = RequestHeader {
+            dbname: options.database_name.clone(),
+            authorization: auth.map(|auth| AuthHeader {
+                auth_scheme: Some(auth),
+            }),
+            ..Default::default()
+        };
+
+        Ok(Self {
+            endpoints: endpoints.into(),
+            header,
+            next: Arc::new(AtomicUsize::new(0)),
+            retry: options.retry,
+        })
+    }
+
+    /// Returns all endpoints in the order to try them, starting from the next
+    /// one in round robin order. Ejected endpoints are tried last.
+    fn candidates(&self) -> Vec<&Endpoint> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let now = Instant::now();
+        let (mut healthy, ejected): (Vec<_>, Vec<_>) = (0..self.endpoints.len())
+            .map(|i| &self.endpoints[(start + i) % self.endpoints.len()])
+            .partition(|endpoint| !endpoint.is_ejected(now));
+        healthy.extend(ejected);
+        healthy
+    }
+
+    /// Returns true if bulk writers can be created on the endpoints.
+    pub fn supports_bulk(&self) -> bool {
+        self.endpoints[0].database.is_some()
+    }
+
+    /// Returns the endpoint for a long-lived stream.
+    pub fn pick(&self) -> &Endpoint {
+        self.candidates()[0]
+    }
+
+    /// Same as [Endpoints::call], but retries the call with the retry policy.
+    /// `attempts` is set to the number of calls made.
+    pub async fn call_with_retry<'a, R, T, F, Fut>(
+        &'a self,
+        request: R,
+        attempts: &mut u32,
+        f: F,
+    ) -> greptimedb_ingester::Result<T>
+    where
+        R: Clone,
+        F: Fn(&'a Endpoint, R) -> Fut,
+        Fut: Future<Output = greptimedb_ingester::Result<T>>,
+    {
+        self.retry
+            .retry(request, attempts, |request| self.call(request, &f))
+            .await
+    }
+
+    /// Sends `request` by `f` to endpoints until one of them is available.
+    pub async fn call<'a, R, T, F, Fut>(
+        &'a self,
+        request: R,
+        f: F,
+    ) -> greptimedb_ingester::Result<T>
+    where
+        R: Clone,
+        F: Fn(&'a Endpoint, R) -> Fut,
+        Fut: Future<Output = greptimedb_ingester::Result<T>>,
+    {
+        let candidates = self.candidates();
+        let mut request = Some(request);
+        let mut result = None;
+        for (i, endpoint) in candidates.iter().enumerate() {
+            // Only clone the request if it may be retried.
+            let request = if i + 1 == candidates.len() {
+                request.take()
+            } else {
+                request.clone()
+            };
+            // safety: request is only taken by the last endpoint.
+            match f(endpoint, request.unwrap()).await {
+                Err(e) if is_unavailable(&e) => {
+                    warn!(endpoint = endpoint.addr, err = %e, "Endpoint is unavailable");
+                    endpoint.eject();
+                    result = Some(Err(e));
+                }
+                res => {
+                    if res.is_ok() {
+                        endpoint.recover();
+                    }
+                    return res;
+                }
+            }
+        }
+        // safety: there is at least one endpoint.
+        result.unwrap()
+    }
+}
+
+/// Creates a client on a lazily connected channel to `addr`, configured like
+/// channels of the ingester.
+fn connect(
+    addr: &str,
+    config: &ChannelConfig,
+    tls: Option<&ClientTlsConfig>,
+) -> error::Result<GreptimeDatabaseClient<Channel>> {
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    let mut endpoint = tonic::transport::Endpoint::from_shared(format!("{scheme}://{addr}"))
+        .context(error::CreateChannelSnafu { addr })?
+        .tcp_keepalive(config.tcp_keepalive)
+        .tcp_nodelay(config.tcp_nodelay);
+    if let Some(timeout) = config.connect_timeout {
+        endpoint = endpoint.connect_timeout(timeout);
+    }
+    if let Some(interval) = config.http2_keep_alive_interval {
+        endpoint = endpoint.http2_keep_alive_interval(interval);
+    }
+    if let Some(enabled) = config.http2_keep_alive_while_idle {
+        endpoint = endpoint.keep_alive_while_idle(enabled);
+    }
+    if let Some(tls) = tls {
+        endpoint = endpoint
+            .tls_config(tls.clone())
+            .context(error::CreateChannelSnafu { addr })?;
+    }
+
+    let mut client = GreptimeDatabaseClient::new(endpoint.connect_lazy())
+        .max_decoding_message_size(config.max_recv_message_size as usize)
+        .max_encoding_message_size(config.max_send_message_size as usize);
+    if config.send_compression {
+        client = client.send_compressed(CompressionEncoding::Zstd);
+    }
+    if config.accept_compression {
+        client = client.accept_compressed(CompressionEncoding::Zstd);
+    }
+    Ok(client)
+}
+
+/// Creates a [Database] of the ingester connecting to `addr` only, for bulk writers.
+fn new_database(
+    addr: &str,
+    options: &ClientOptions,
+    auth: Option<AuthScheme>,
+) -> error::Result<Database> {
+    let channel_config = options.channel_config();
+    let channel_manager = if channel_config.client_tls.is_some() {
+        ChannelManager::with_tls_config(channel_config)
+            .map_err(Box::new)
+            .context(error::InvalidTlsConfigSnafu)?
+    } else {
+        ChannelManager::with_config(channel_config)
+    };
+    let grpc_client =
+        greptimedb_ingester::client::Client::with_manager_and_urls(channel_manager, [addr]);
+    let mut database = Database::new_with_dbname(&options.database_name, grpc_client);
+    if let Some(auth) = auth {
+        database.set_auth(auth);
+    }
+    Ok(database)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{StubOptions, StubServer};
+    use greptimedb_ingester::api::v1::GreptimeRequest;
+    use greptimedb_ingester::api::v1::greptime_request::Request;
+
+    #[test]
+    fn call_fails_over_and_ejects_unavailable_endpoints() {
+        let server = StubServer::start(StubOptions::default());
+        let mut options = ClientOptions::new("public".to_string());
+        // Nothing listens on port 1.
+        options.add_endpoint("127.0.0.1:1".to_string());
+        options.add_endpoint(server.addr());
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let endpoints = Endpoints::new(&options, None).unwrap();
+        let request = GreptimeRequest {
+            request: Some(Request::RowInserts(Default::default())),
+            ..Default::default()
+        };
+
+        for _ in 0..4 {
+            let addr = runtime
+                .block_on(
+                    endpoints.call(request.clone(), |endpoint, request| async move {
+                        endpoint.client.clone().handle(request).await?;
+                        Ok(endpoint.addr.clone())
+                    }),
+                )
+                .unwrap();
+            assert_eq!(addr, server.addr());
+        }
+        assert!(endpoints.endpoints[0].is_ejected(Instant::now()));
+        assert!(!endpoints.endpoints[1].is_ejected(Instant::now()));
+        assert_eq!(endpoints.pick().addr, server.addr());
+    }
+}
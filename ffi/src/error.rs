@@ -13,10 +13,13 @@
 // limitations under the License.
 
 use backtrace::Backtrace;
+use greptimedb_ingester::arrow_schema::ArrowError;
 use prost::UnknownEnumValue;
 use snafu::{Location, Snafu};
+use std::panic::AssertUnwindSafe;
 use std::str::Utf8Error;
 use std::sync::Once;
+use std::time::Duration;
 use std::{fmt, panic};
 use strum::EnumString;
 use tracing::error;
@@ -31,6 +34,13 @@ pub enum StatusCode {
     InvalidArgument = 1002,
     InvalidPointer = 1003,
     IllegalState = 1004,
+    Timeout = 1005,
+    Unauthenticated = 1006,
+    PermissionDenied = 1007,
+    TableNotFound = 1008,
+    SchemaConflict = 1009,
+    RequestTooLarge = 1010,
+    Panic = 1011,
 }
 
 impl fmt::Display for StatusCode {
@@ -49,6 +59,132 @@ pub enum Error {
         location: Location,
     },
 
+    #[snafu(display("Failed to delete req, location: {}, source: {}", location, source))]
+    DeleteReq {
+        source: Box<greptimedb_ingester::Error>,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display("Failed to build runtime, location: {}, source: {}", location, source))]
+    BuildRuntime {
+        source: std::io::Error,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display("Logging is already initialized, location: {}", location))]
+    LoggerInitialized {
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display("Invalid logging options: {}, location: {}", reason, location))]
+    InvalidLoggingOptions {
+        reason: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Invalid log level: {}, location: {}, source: {}",
+        level,
+        location,
+        source
+    ))]
+    InvalidLogLevel {
+        level: String,
+        source: tracing_subscriber::filter::ParseError,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Failed to create log file in: {}, location: {}, source: {}",
+        dir,
+        location,
+        source
+    ))]
+    CreateLogFile {
+        dir: String,
+        source: tracing_appender::rolling::InitError,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Failed to set global tracing subscriber, location: {}, source: {}",
+        location,
+        source
+    ))]
+    SetGlobalSubscriber {
+        source: tracing::subscriber::SetGlobalDefaultError,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display("Request timed out after {:?}, location: {}", timeout, location))]
+    Timeout {
+        timeout: Duration,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display("At least one endpoint is required, location: {}", location))]
+    EmptyEndpoints {
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display("Invalid TLS config, location: {}, source: {}", location, source))]
+    InvalidTlsConfig {
+        source: Box<greptimedb_ingester::Error>,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display("Invalid TLS options: {}, location: {}", reason, location))]
+    InvalidTlsOptions {
+        reason: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Failed to read TLS file: {}, location: {}, source: {}",
+        path,
+        location,
+        source
+    ))]
+    ReadTlsFile {
+        path: String,
+        source: std::io::Error,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Failed to create channel to: {}, location: {}, source: {}",
+        addr,
+        location,
+        source
+    ))]
+    CreateChannel {
+        addr: String,
+        source: tonic::transport::Error,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Bulk writers only support mutual TLS without server name override, location: {}",
+        location
+    ))]
+    UnsupportedBulkTls {
+        #[snafu(implicit)]
+        location: Location,
+    },
+
     #[snafu(display("Unsupported data type: {}, location: {}", data_type, location,))]
     UnsupportedDataType {
         data_type: i32,
@@ -69,6 +205,17 @@ pub enum Error {
         location: Location,
     },
 
+    #[snafu(display(
+        "Null value is not allowed for non-field column: {}, location: {}",
+        column,
+        location
+    ))]
+    NullValue {
+        column: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
     #[snafu(display("Null pointer, location: {:?}", location))]
     NullPointer {
         #[snafu(implicit)]
@@ -101,21 +248,327 @@ pub enum Error {
         #[snafu(implicit)]
         location: Location,
     },
+
+    #[snafu(display(
+        "Invalid decimal column def, name: {}, precision: {}, scale: {}, location: {:?}",
+        name,
+        precision,
+        scale,
+        location
+    ))]
+    InvalidDecimalType {
+        name: String,
+        precision: i32,
+        scale: i32,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Decimal value {} overflows precision {} of column: {}, location: {}",
+        value,
+        precision,
+        column,
+        location
+    ))]
+    DecimalOverflow {
+        column: String,
+        value: i128,
+        precision: i32,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Invalid JSON value of column: {}, location: {}, source: {}",
+        column,
+        location,
+        source
+    ))]
+    InvalidJson {
+        column: String,
+        source: serde_json::Error,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Invalid vector column def, name: {}, dim: {}, location: {:?}",
+        name,
+        dim,
+        location
+    ))]
+    InvalidVectorType {
+        name: String,
+        dim: u32,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Vector length {} does not match dim {} of column: {}, location: {}",
+        len,
+        dim,
+        column,
+        location
+    ))]
+    VectorDimMismatch {
+        column: String,
+        dim: u32,
+        len: usize,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display("Column not found: {}, location: {}", name, location))]
+    ColumnNotFound {
+        name: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Invalid index options of column: {}, reason: {}, location: {}",
+        name,
+        reason,
+        location
+    ))]
+    InvalidIndexOptions {
+        name: String,
+        reason: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display("Invalid hint, key: {}, value: {}, location: {}", key, value, location))]
+    InvalidHint {
+        key: String,
+        value: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Hints of tables written in one request differ, tables: {} and {}, location: {}",
+        table_name,
+        other,
+        location
+    ))]
+    MismatchedHints {
+        table_name: String,
+        other: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Hints are not supported by streams, table: {}, location: {}",
+        table_name,
+        location
+    ))]
+    UnsupportedHints {
+        table_name: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display("Stream is closed, location: {}", location))]
+    StreamClosed {
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display("Illegal response: {}, location: {}", msg, location))]
+    IllegalResponse {
+        msg: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Value {} is out of range of column: {}, location: {}",
+        value,
+        column,
+        location
+    ))]
+    ValueOutOfRange {
+        column: String,
+        value: i64,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Column {} of table {} is neither a tag nor the time index, location: {}",
+        column,
+        table_name,
+        location
+    ))]
+    InvalidDeleteColumn {
+        table_name: String,
+        column: String,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display("Invalid compression: {}, location: {}", compression, location))]
+    InvalidCompression {
+        compression: i32,
+        #[snafu(implicit)]
+        location: Location,
+    },
+
+    #[snafu(display(
+        "Failed to create record batch, location: {}, source: {}",
+        location,
+        source
+    ))]
+    CreateRecordBatch {
+        source: ArrowError,
+        #[snafu(implicit)]
+        location: Location,
+    },
+}
+
+impl Error {
+    /// Returns the GreptimeDB status code if the error is sent by the server.
+    pub fn server_error_code(&self) -> Option<u32> {
+        match self {
+            Error::InsertReq { source, .. } | Error::DeleteReq { source, .. } => {
+                server_error_code(source)
+            }
+            _ => None,
+        }
+    }
 }
 
 impl ErrorExt for Error {
     fn status_code(&self) -> StatusCode {
         match self {
             Error::UnsupportedDataType { .. } => StatusCode::InvalidArgument,
-            Error::InsertReq { .. } => StatusCode::Unknown,
+            Error::InsertReq { source, .. } | Error::DeleteReq { source, .. } => {
+                ingester_status_code(source)
+            }
+            Error::InvalidTlsConfig { .. } => StatusCode::InvalidArgument,
+            Error::InvalidTlsOptions { .. } => StatusCode::InvalidArgument,
+            Error::ReadTlsFile { .. } => StatusCode::InvalidArgument,
+            Error::CreateChannel { .. } => StatusCode::InvalidArgument,
+            Error::UnsupportedBulkTls { .. } => StatusCode::InvalidArgument,
+            Error::EmptyEndpoints { .. } => StatusCode::InvalidArgument,
+            Error::Timeout { .. } => StatusCode::Timeout,
+            Error::BuildRuntime { .. } => StatusCode::Unknown,
+            Error::LoggerInitialized { .. } => StatusCode::IllegalState,
+            Error::InvalidLoggingOptions { .. } => StatusCode::InvalidArgument,
+            Error::InvalidLogLevel { .. } => StatusCode::InvalidArgument,
+            Error::CreateLogFile { .. } => StatusCode::InvalidArgument,
+            Error::SetGlobalSubscriber { .. } => StatusCode::IllegalState,
             Error::SchemaMismatch { .. } => StatusCode::InvalidArgument,
+            Error::NullValue { .. } => StatusCode::InvalidArgument,
             Error::NullPointer { .. } => StatusCode::InvalidPointer,
             Error::InvalidCString { .. } => StatusCode::InvalidArgument,
             Error::InvalidColumnDef { .. } => StatusCode::InvalidArgument,
+            Error::InvalidDecimalType { .. } => StatusCode::InvalidArgument,
+            Error::DecimalOverflow { .. } => StatusCode::InvalidArgument,
+            Error::InvalidJson { .. } => StatusCode::InvalidArgument,
+            Error::InvalidVectorType { .. } => StatusCode::InvalidArgument,
+            Error::VectorDimMismatch { .. } => StatusCode::InvalidArgument,
+            Error::ColumnNotFound { .. } => StatusCode::InvalidArgument,
+            Error::InvalidIndexOptions { .. } => StatusCode::InvalidArgument,
+            Error::InvalidHint { .. } => StatusCode::InvalidArgument,
+            Error::MismatchedHints { .. } => StatusCode::InvalidArgument,
+            Error::UnsupportedHints { .. } => StatusCode::InvalidArgument,
+            Error::StreamClosed { .. } => StatusCode::IllegalState,
+            Error::IllegalResponse { .. } => StatusCode::Unknown,
+            Error::ValueOutOfRange { .. } => StatusCode::InvalidArgument,
+            Error::CreateRecordBatch { .. } => StatusCode::InvalidArgument,
+            Error::InvalidCompression { .. } => StatusCode::InvalidArgument,
+            Error::InvalidDeleteColumn { .. } => StatusCode::InvalidArgument,
         }
     }
 }
 
+/// Metadata key of the GreptimeDB status code in gRPC error responses.
+const SERVER_ERROR_CODE_KEY: &str = "x-greptime-err-code";
+
+/// Status codes of GreptimeDB that are mapped to a [StatusCode], see
+/// `common_error::status_code::StatusCode` of GreptimeDB.
+mod server_code {
+    pub const DEADLINE_EXCEEDED: u32 = 1008;
+    pub const TABLE_NOT_FOUND: u32 = 4001;
+    pub const TABLE_COLUMN_NOT_FOUND: u32 = 4002;
+    pub const TABLE_COLUMN_EXISTS: u32 = 4003;
+    pub const DATABASE_NOT_FOUND: u32 = 4004;
+    pub const REGION_NOT_READY: u32 = 4008;
+    pub const REGION_BUSY: u32 = 4009;
+    pub const TABLE_UNAVAILABLE: u32 = 4010;
+    pub const STORAGE_UNAVAILABLE: u32 = 5000;
+    pub const RUNTIME_RESOURCES_EXHAUSTED: u32 = 6000;
+    pub const RATE_LIMITED: u32 = 6001;
+    pub const USER_NOT_FOUND: u32 = 7000;
+    pub const INVALID_AUTH_HEADER: u32 = 7004;
+    pub const ACCESS_DENIED: u32 = 7005;
+    pub const PERMISSION_DENIED: u32 = 7006;
+}
+
+/// Returns true if the server cannot be reached, so the request may succeed
+/// on another endpoint.
+pub fn is_unavailable(e: &greptimedb_ingester::Error) -> bool {
+    matches!(e, greptimedb_ingester::Error::Server { status, .. } if status.code() == tonic::Code::Unavailable)
+}
+
+/// Returns the GreptimeDB status code carried by a server error, if any.
+pub fn server_error_code(e: &greptimedb_ingester::Error) -> Option<u32> {
+    match e {
+        greptimedb_ingester::Error::Server { status, .. } => status
+            .metadata()
+            .get(SERVER_ERROR_CODE_KEY)?
+            .to_str()
+            .ok()?
+            .parse()
+            .ok(),
+        _ => None,
+    }
+}
+
+pub(crate) fn ingester_status_code(e: &greptimedb_ingester::Error) -> StatusCode {
+    use server_code::*;
+
+    let status = match e {
+        greptimedb_ingester::Error::Server { status, .. } => status,
+        greptimedb_ingester::Error::RequestTimeout { .. } => return StatusCode::Timeout,
+        _ => return StatusCode::Unknown,
+    };
+    // The server code is more precise than the gRPC code derived from it.
+    match server_error_code(e) {
+        Some(DEADLINE_EXCEEDED) => return StatusCode::Timeout,
+        Some(TABLE_NOT_FOUND | DATABASE_NOT_FOUND) => return StatusCode::TableNotFound,
+        Some(TABLE_COLUMN_NOT_FOUND | TABLE_COLUMN_EXISTS) => return StatusCode::SchemaConflict,
+        Some(
+            REGION_NOT_READY
+            | REGION_BUSY
+            | TABLE_UNAVAILABLE
+            | STORAGE_UNAVAILABLE
+            | RUNTIME_RESOURCES_EXHAUSTED
+            | RATE_LIMITED,
+        ) => return StatusCode::ServerUnavailable,
+        Some(USER_NOT_FOUND..=INVALID_AUTH_HEADER) => return StatusCode::Unauthenticated,
+        Some(ACCESS_DENIED | PERMISSION_DENIED) => return StatusCode::PermissionDenied,
+        _ => {}
+    }
+    match status.code() {
+        tonic::Code::Unavailable | tonic::Code::ResourceExhausted => StatusCode::ServerUnavailable,
+        tonic::Code::DeadlineExceeded => StatusCode::Timeout,
+        tonic::Code::Unauthenticated => StatusCode::Unauthenticated,
+        tonic::Code::PermissionDenied => StatusCode::PermissionDenied,
+        tonic::Code::NotFound => StatusCode::TableNotFound,
+        tonic::Code::AlreadyExists | tonic::Code::FailedPrecondition => StatusCode::SchemaConflict,
+        // Raised by tonic if a message exceeds the max encoding or decoding size.
+        tonic::Code::OutOfRange => StatusCode::RequestTooLarge,
+        tonic::Code::InvalidArgument => StatusCode::InvalidArgument,
+        _ => StatusCode::Unknown,
+    }
+}
+
 pub trait ErrorExt: std::error::Error {
     fn status_code(&self) -> StatusCode {
         StatusCode::Unknown
@@ -127,6 +580,11 @@ macro_rules! ensure_not_null {
     ($ptr: expr) => {
         if $ptr.is_null() {
             tracing::error!("[PANIC] {} ptr cannot be null", stringify!($ptr));
+            $crate::last_error::set(
+                $crate::error::StatusCode::InvalidPointer,
+                format!("{} ptr cannot be null", stringify!($ptr)),
+                None,
+            );
             return StatusCode::InvalidPointer as i32;
         }
     };
@@ -145,6 +603,24 @@ pub fn set_panic_hook() {
     });
 }
 
+/// Runs the body of an exported function, so that a panic is returned as
+/// `Panic` and recorded as the last error instead of unwinding into C.
+pub fn catch_panic(f: impl FnOnce() -> libc::c_int) -> libc::c_int {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(code) => code,
+        Err(payload) => {
+            let msg = payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("unknown panic");
+            error!("Caught panic at FFI boundary: {}", msg);
+            crate::last_error::set(StatusCode::Panic, format!("Panic: {msg}"), None);
+            StatusCode::Panic as i32
+        }
+    }
+}
+
 fn log_panic(panic: &panic::PanicHookInfo<'_>) {
     let backtrace = format!("{:?}", Backtrace::new());
 
@@ -160,3 +636,71 @@ fn log_panic(panic: &panic::PanicHookInfo<'_>) {
         None => error!("Panic: {:?}, backtrace: {:#?}", panic, backtrace),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snafu::ResultExt;
+    use tonic::{Code, Status};
+
+    fn insert_error(status: Status) -> Error {
+        Err::<(), _>(Box::new(greptimedb_ingester::Error::from(status)))
+            .context(InsertReqSnafu)
+            .unwrap_err()
+    }
+
+    fn server_error(code: Code, server_code: u32) -> Error {
+        let mut status = Status::new(code, "server error");
+        status
+            .metadata_mut()
+            .insert(SERVER_ERROR_CODE_KEY, server_code.into());
+        insert_error(status)
+    }
+
+    #[test]
+    fn ingester_errors_map_to_status_codes() {
+        let cases = [
+            (Code::Unavailable, StatusCode::ServerUnavailable),
+            (Code::DeadlineExceeded, StatusCode::Timeout),
+            (Code::Unauthenticated, StatusCode::Unauthenticated),
+            (Code::PermissionDenied, StatusCode::PermissionDenied),
+            (Code::NotFound, StatusCode::TableNotFound),
+            (Code::AlreadyExists, StatusCode::SchemaConflict),
+            (Code::OutOfRange, StatusCode::RequestTooLarge),
+            (Code::InvalidArgument, StatusCode::InvalidArgument),
+            (Code::Internal, StatusCode::Unknown),
+        ];
+        for (code, expected) in cases {
+            let err = insert_error(Status::new(code, "error"));
+            assert_eq!(err.status_code(), expected, "{code:?}");
+        }
+
+        // Server codes take precedence over gRPC codes.
+        let cases = [
+            (Code::NotFound, 4001, StatusCode::TableNotFound),
+            (Code::InvalidArgument, 4002, StatusCode::SchemaConflict),
+            (Code::Unavailable, 4009, StatusCode::ServerUnavailable),
+            (Code::Unauthenticated, 7002, StatusCode::Unauthenticated),
+            (Code::PermissionDenied, 7006, StatusCode::PermissionDenied),
+            (Code::Internal, 1008, StatusCode::Timeout),
+            (Code::InvalidArgument, 1004, StatusCode::InvalidArgument),
+        ];
+        for (code, server_code, expected) in cases {
+            let err = server_error(code, server_code);
+            assert_eq!(err.status_code(), expected, "{server_code}");
+        }
+    }
+
+    #[test]
+    fn catch_panic_records_panic() {
+        crate::last_error::clear();
+        assert_eq!(catch_panic(|| StatusCode::Success as i32), 0);
+        assert_eq!(crate::last_error::code(), StatusCode::Success);
+
+        let code = catch_panic(|| panic!("boom {}", 1));
+        assert_eq!(code, StatusCode::Panic as i32);
+        assert_eq!(crate::last_error::code(), StatusCode::Panic);
+        let msg = unsafe { std::ffi::CStr::from_ptr(crate::last_error::message().unwrap()) };
+        assert_eq!(msg.to_str().unwrap(), "Panic: boom 1");
+    }
+}
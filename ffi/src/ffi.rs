@@ -12,19 +12,109 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::bulk::{BulkWriter, BulkWriterOptions};
+use crate::column::ColumnData;
+use crate::error;
 use crate::error::ErrorExt;
-use crate::error::StatusCode;
-use crate::row::{RowBuilder, Value};
+use crate::error::{StatusCode, catch_panic};
+use crate::last_error;
+use crate::logger::{LoggingOptions, init_logger_with_options};
+use crate::options::{ClientOptions, TlsOptions};
+use crate::retry::RetryPolicy;
+use crate::row::{ColumnIndexOptions, RowBuilder, Value};
+use crate::stream::StreamInserter;
 use crate::util::convert_c_string;
+use crate::writer::{BatchWriter, BatchWriterOptions};
 use crate::{Client, ensure_not_null};
+use std::collections::HashSet;
 use std::ptr;
+use std::time::Duration;
 use tracing::error;
 
+/// Callback of async writes, called with the status code and the number of affected rows.
+pub type WriteCallback =
+    unsafe extern "C" fn(user_data: *mut libc::c_void, status: libc::c_int, affected_rows: u32);
+
+/// Calls the write callback exactly once, with `IllegalState` if the write is
+/// dropped before completion, e.g. when the client is freed.
+struct WriteCompletion {
+    callback: Option<WriteCallback>,
+    user_data: *mut libc::c_void,
+}
+
+// safety: the caller of async writes guarantees `user_data` can be used on other threads.
+unsafe impl Send for WriteCompletion {}
+
+impl WriteCompletion {
+    fn complete(mut self, result: error::Result<u32>) {
+        match result {
+            Ok(affected_rows) => self.invoke(StatusCode::Success, affected_rows),
+            Err(e) => {
+                error!(err.msg = %e, err.code = %e.status_code(), "Failed async write");
+                last_error::set_error(&e);
+                self.invoke(e.status_code(), 0);
+            }
+        }
+    }
+
+    fn invoke(&mut self, status: StatusCode, affected_rows: u32) {
+        if let Some(callback) = self.callback.take() {
+            unsafe { callback(self.user_data, status as i32, affected_rows) };
+        }
+    }
+}
+
+impl Drop for WriteCompletion {
+    fn drop(&mut self) {
+        self.invoke(StatusCode::IllegalState, 0);
+    }
+}
+
+/// Reports results of background flushes of batch writers.
+struct FlushCallback {
+    callback: Option<WriteCallback>,
+    user_data: *mut libc::c_void,
+}
+
+// safety: the caller of batch writers guarantees `user_data` can be used on other threads.
+unsafe impl Send for FlushCallback {}
+unsafe impl Sync for FlushCallback {}
+
+impl FlushCallback {
+    fn report(&self, result: error::Result<u32>) {
+        let (status, affected_rows) = match result {
+            Ok(affected_rows) => (StatusCode::Success, affected_rows),
+            Err(e) => {
+                error!(err.msg = %e, err.code = %e.status_code(), "Failed to flush batch writer");
+                last_error::set_error(&e);
+                (e.status_code(), 0)
+            }
+        };
+        if let Some(callback) = self.callback {
+            unsafe { callback(self.user_data, status as i32, affected_rows) };
+        }
+    }
+}
+
+/// Logs and records a failure detected by the FFI layer itself. Invalid
+/// pointers are logged with the `[PANIC]` tag like [ensure_not_null], which
+/// is not part of the recorded message.
+fn fail(code: StatusCode, msg: &str) -> libc::c_int {
+    if code == StatusCode::InvalidPointer {
+        error!("[PANIC] {msg}");
+    } else {
+        error!("{msg}");
+    }
+    last_error::set(code, msg.to_string(), None);
+    code as i32
+}
+
 macro_rules! handle_result {
     ($expr: expr) => {
         match $expr {
             Err(e) => {
                 error!(err.msg = %e, err.code = %e.status_code(), "Failed FFI invocation");
+                last_error::set_error(&e);
                 return e.status_code() as i32;
             }
             Ok(res) => res,
@@ -37,29 +127,33 @@ pub unsafe extern "C" fn _new_row_builder(
     table_name: *const libc::c_char,
     res_ptr: *mut *const RowBuilder,
 ) -> libc::c_int {
-    ensure_not_null!(table_name);
-    ensure_not_null!(res_ptr);
-    let col_name = handle_result!(convert_c_string(table_name));
-    unsafe {
-        *res_ptr = Box::into_raw(Box::new(RowBuilder::new(col_name)));
-    }
-    StatusCode::Success as i32
+    catch_panic(|| {
+        ensure_not_null!(table_name);
+        ensure_not_null!(res_ptr);
+        let col_name = handle_result!(convert_c_string(table_name));
+        unsafe {
+            *res_ptr = Box::into_raw(Box::new(RowBuilder::new(col_name)));
+        }
+        StatusCode::Success as i32
+    })
 }
 
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn free_row_builder(res_ptr: *mut *mut RowBuilder) -> libc::c_int {
-    if res_ptr.is_null() {
-        return StatusCode::Success as i32;
-    }
-    let row_builder_ptr = unsafe { &mut *res_ptr };
+    catch_panic(|| {
+        if res_ptr.is_null() {
+            return StatusCode::Success as i32;
+        }
+        let row_builder_ptr = unsafe { &mut *res_ptr };
 
-    if row_builder_ptr.is_null() {
-        return StatusCode::Success as i32;
-    }
-    let _ = unsafe { Box::from_raw(*row_builder_ptr) };
-    *row_builder_ptr = ptr::null_mut();
+        if row_builder_ptr.is_null() {
+            return StatusCode::Success as i32;
+        }
+        let _ = unsafe { Box::from_raw(*row_builder_ptr) };
+        *row_builder_ptr = ptr::null_mut();
 
-    StatusCode::Success as i32
+        StatusCode::Success as i32
+    })
 }
 
 #[unsafe(no_mangle)]
@@ -69,14 +163,75 @@ pub unsafe extern "C" fn _define_column(
     data_type: libc::c_int,
     semantic_type: libc::c_int,
 ) -> libc::c_int {
-    ensure_not_null!(row_builder);
-    ensure_not_null!(col_name);
+    catch_panic(|| {
+        ensure_not_null!(row_builder);
+        ensure_not_null!(col_name);
 
-    let builder = unsafe { &mut *row_builder };
-    let col_name = handle_result!(convert_c_string(col_name));
+        let builder = unsafe { &mut *row_builder };
+        let col_name = handle_result!(convert_c_string(col_name));
 
-    handle_result!(builder.add_col(col_name, data_type, semantic_type));
-    StatusCode::Success as i32
+        handle_result!(builder.add_col(col_name, data_type, semantic_type));
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn _define_decimal128_column(
+    row_builder: *mut RowBuilder,
+    col_name: *const libc::c_char,
+    semantic_type: libc::c_int,
+    precision: libc::c_int,
+    scale: libc::c_int,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(row_builder);
+        ensure_not_null!(col_name);
+
+        let builder = unsafe { &mut *row_builder };
+        let col_name = handle_result!(convert_c_string(col_name));
+
+        handle_result!(builder.add_decimal128_col(col_name, semantic_type, precision, scale));
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn _define_vector_column(
+    row_builder: *mut RowBuilder,
+    col_name: *const libc::c_char,
+    semantic_type: libc::c_int,
+    dim: libc::c_uint,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(row_builder);
+        ensure_not_null!(col_name);
+
+        let builder = unsafe { &mut *row_builder };
+        let col_name = handle_result!(convert_c_string(col_name));
+
+        handle_result!(builder.add_vector_col(col_name, semantic_type, dim));
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn _set_column_index_options(
+    row_builder: *mut RowBuilder,
+    col_name: *const libc::c_char,
+    index_options: *const ColumnIndexOptions,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(row_builder);
+        ensure_not_null!(col_name);
+        ensure_not_null!(index_options);
+
+        let builder = unsafe { &mut *row_builder };
+        let col_name = handle_result!(convert_c_string(col_name));
+        let index_options = unsafe { &*index_options };
+
+        handle_result!(builder.set_col_index_options(&col_name, index_options));
+        StatusCode::Success as i32
+    })
 }
 
 #[unsafe(no_mangle)]
@@ -85,14 +240,76 @@ pub unsafe extern "C" fn add_row(
     values: *const Value,
     value_len: libc::size_t,
 ) -> libc::c_int {
-    ensure_not_null!(row_builder);
-    ensure_not_null!(values);
+    catch_panic(|| {
+        ensure_not_null!(row_builder);
+        ensure_not_null!(values);
+
+        let builder = unsafe { &mut *row_builder };
+
+        let values = unsafe { std::slice::from_raw_parts(values, value_len) };
+        handle_result!(unsafe { builder.add_row(values) });
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn add_nullable_row(
+    row_builder: *mut RowBuilder,
+    values: *const Value,
+    nulls: *const bool,
+    value_len: libc::size_t,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(row_builder);
+        ensure_not_null!(values);
+        ensure_not_null!(nulls);
+
+        let builder = unsafe { &mut *row_builder };
+
+        let values = unsafe { std::slice::from_raw_parts(values, value_len) };
+        let nulls = unsafe { std::slice::from_raw_parts(nulls, value_len) };
+        handle_result!(unsafe { builder.add_nullable_row(values, nulls) });
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn add_columns(
+    row_builder: *mut RowBuilder,
+    columns: *const ColumnData,
+    column_len: libc::size_t,
+    rows: libc::size_t,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(row_builder);
+        ensure_not_null!(columns);
 
-    let builder = unsafe { &mut *row_builder };
+        let builder = unsafe { &mut *row_builder };
 
-    let values = unsafe { std::slice::from_raw_parts(values, value_len) };
-    handle_result!(unsafe { builder.add_row(values) });
-    StatusCode::Success as i32
+        let columns = unsafe { std::slice::from_raw_parts(columns, column_len) };
+        handle_result!(unsafe { builder.add_columns(columns, rows) });
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn set_hint(
+    row_builder: *mut RowBuilder,
+    key: *const libc::c_char,
+    value: *const libc::c_char,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(row_builder);
+        ensure_not_null!(key);
+        ensure_not_null!(value);
+
+        let builder = unsafe { &mut *row_builder };
+        let key = handle_result!(convert_c_string(key));
+        let value = handle_result!(convert_c_string(value));
+
+        handle_result!(builder.set_hint(key, value));
+        StatusCode::Success as i32
+    })
 }
 
 #[unsafe(no_mangle)]
@@ -102,59 +319,778 @@ pub unsafe extern "C" fn new_client(
     username: *const libc::c_char,
     password: *const libc::c_char,
     res_ptr: *mut *const Client,
+) -> libc::c_int {
+    catch_panic(|| unsafe {
+        new_client_with_args(
+            database_name,
+            endpoint,
+            username,
+            password,
+            ptr::null(),
+            res_ptr,
+        )
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn new_tls_client(
+    database_name: *const libc::c_char,
+    endpoint: *const libc::c_char,
+    username: *const libc::c_char,
+    password: *const libc::c_char,
+    tls: *const TlsOptions,
+    res_ptr: *mut *const Client,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(tls);
+        unsafe { new_client_with_args(database_name, endpoint, username, password, tls, res_ptr) }
+    })
+}
+
+/// Creates a client from the fixed arguments of [new_client] and [new_tls_client].
+unsafe fn new_client_with_args(
+    database_name: *const libc::c_char,
+    endpoint: *const libc::c_char,
+    username: *const libc::c_char,
+    password: *const libc::c_char,
+    tls: *const TlsOptions,
+    res_ptr: *mut *const Client,
 ) -> libc::c_int {
     ensure_not_null!(database_name);
     ensure_not_null!(endpoint);
-    ensure_not_null!(res_ptr);
-    let database_name = handle_result!(convert_c_string(database_name));
-    let endpoint = handle_result!(convert_c_string(endpoint));
-    let username = if username.is_null() {
-        None
-    } else {
-        Some(handle_result!(convert_c_string(username)))
-    };
-    let password = if password.is_null() {
-        None
-    } else {
-        Some(handle_result!(convert_c_string(password)))
-    };
+    let mut options = ClientOptions::new(handle_result!(convert_c_string(database_name)));
+    options.add_endpoint(handle_result!(convert_c_string(endpoint)));
+    let code = unsafe { client_options_set_auth(&mut options, username, password) };
+    if code != StatusCode::Success as i32 {
+        return code;
+    }
+    if !tls.is_null() {
+        options.set_tls(handle_result!(unsafe { &*tls }.to_tls_config()));
+    }
+    unsafe { new_client_with_options(&options, res_ptr) }
+}
 
-    let auth = match (username, password) {
-        (None, None) => None,
-        (Some(u), Some(p)) => Some((u, p)),
-        (Some(u), None) => Some((u, String::new())),
-        (None, Some(_)) => return StatusCode::InvalidArgument as i32,
-    };
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn init_logging(options: *const LoggingOptions) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(options);
+        let options = handle_result!(unsafe { (*options).to_logging_config() });
+        handle_result!(init_logger_with_options(&options));
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn new_client_options(
+    database_name: *const libc::c_char,
+    res_ptr: *mut *const ClientOptions,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(database_name);
+        ensure_not_null!(res_ptr);
+        let database_name = handle_result!(convert_c_string(database_name));
+        let options = ClientOptions::new(database_name);
+        unsafe { *res_ptr = Box::into_raw(Box::new(options)) };
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn client_options_add_endpoint(
+    options: *mut ClientOptions,
+    endpoint: *const libc::c_char,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(options);
+        ensure_not_null!(endpoint);
+        let options = unsafe { &mut *options };
+        options.add_endpoint(handle_result!(convert_c_string(endpoint)));
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn client_options_set_auth(
+    options: *mut ClientOptions,
+    username: *const libc::c_char,
+    password: *const libc::c_char,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(options);
+        let options = unsafe { &mut *options };
+        let username = if username.is_null() {
+            None
+        } else {
+            Some(handle_result!(convert_c_string(username)))
+        };
+        let password = if password.is_null() {
+            None
+        } else {
+            Some(handle_result!(convert_c_string(password)))
+        };
+
+        let auth = match (username, password) {
+            (None, None) => None,
+            (Some(u), Some(p)) => Some((u, p)),
+            (Some(u), None) => Some((u, String::new())),
+            (None, Some(_)) => {
+                return fail(
+                    StatusCode::InvalidArgument,
+                    "password cannot be set without username",
+                );
+            }
+        };
+        options.set_auth(auth);
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn client_options_set_tls(
+    options: *mut ClientOptions,
+    tls: *const TlsOptions,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(options);
+        ensure_not_null!(tls);
+        let options = unsafe { &mut *options };
+        options.set_tls(handle_result!(unsafe { &*tls }.to_tls_config()));
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn client_options_set_timeouts(
+    options: *mut ClientOptions,
+    connect_timeout_ms: u64,
+    timeout_ms: u64,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(options);
+        let options = unsafe { &mut *options };
+        let to_duration = |ms| (ms > 0).then(|| Duration::from_millis(ms));
+        options.set_timeouts(to_duration(connect_timeout_ms), to_duration(timeout_ms));
+        StatusCode::Success as i32
+    })
+}
 
-    let client = handle_result!(Client::new(database_name, endpoint, auth));
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn client_options_set_compression(
+    options: *mut ClientOptions,
+    compression: libc::c_int,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(options);
+        let options = unsafe { &mut *options };
+        handle_result!(options.set_compression(compression));
+        StatusCode::Success as i32
+    })
+}
 
-    unsafe { *res_ptr = Box::into_raw(Box::new(client)) };
-    StatusCode::Success as i32
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn client_options_set_runtime_threads(
+    options: *mut ClientOptions,
+    threads: libc::size_t,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(options);
+        let options = unsafe { &mut *options };
+        options.set_runtime_threads(threads);
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn client_options_set_retry(
+    options: *mut ClientOptions,
+    retry: *const RetryPolicy,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(options);
+        ensure_not_null!(retry);
+        let options = unsafe { &mut *options };
+        options.set_retry(unsafe { *retry });
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn free_client_options(
+    p_options_ptr: *mut *mut ClientOptions,
+) -> libc::c_int {
+    catch_panic(|| {
+        if p_options_ptr.is_null() {
+            return StatusCode::Success as i32;
+        }
+
+        let options_ptr = unsafe { &mut *p_options_ptr };
+        if options_ptr.is_null() {
+            return StatusCode::Success as i32;
+        }
+
+        let _ = unsafe { Box::from_raw(*options_ptr) };
+        *options_ptr = ptr::null_mut();
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn new_client_with_options(
+    options: *const ClientOptions,
+    res_ptr: *mut *const Client,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(options);
+        ensure_not_null!(res_ptr);
+        let options = unsafe { &*options };
+        let client = handle_result!(Client::new(options));
+
+        unsafe { *res_ptr = Box::into_raw(Box::new(client)) };
+        StatusCode::Success as i32
+    })
 }
 
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn write_row(client: *const Client, row: *mut RowBuilder) -> libc::c_int {
-    ensure_not_null!(client);
-    ensure_not_null!(row);
-    let client = unsafe { &*client };
-    let row = unsafe { &mut *row };
-    handle_result!(client.write_row(row));
-    StatusCode::Success as i32
+    catch_panic(|| {
+        ensure_not_null!(client);
+        ensure_not_null!(row);
+        let client = unsafe { &*client };
+        let row = unsafe { &mut *row };
+        handle_result!(client.write_row(row));
+        StatusCode::Success as i32
+    })
 }
 
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn free_client(p_client_ptr: *mut *mut Client) -> libc::c_int {
-    if p_client_ptr.is_null() {
-        return StatusCode::Success as i32;
+pub unsafe extern "C" fn write_row_with_affected_rows(
+    client: *const Client,
+    row: *mut RowBuilder,
+    affected_rows: *mut u32,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(client);
+        ensure_not_null!(row);
+        ensure_not_null!(affected_rows);
+        let client = unsafe { &*client };
+        let row = unsafe { &mut *row };
+        let rows = handle_result!(client.write_row(row));
+        unsafe { *affected_rows = rows };
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn write_rows(
+    client: *const Client,
+    rows: *const *mut RowBuilder,
+    len: libc::size_t,
+    affected_rows: *mut u32,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(client);
+        ensure_not_null!(rows);
+        let client = unsafe { &*client };
+        let mut rows = match unsafe { row_builders(rows, len) } {
+            Ok(rows) => rows,
+            Err(code) => return code as i32,
+        };
+        let rows = handle_result!(client.write_rows(&mut rows));
+        if !affected_rows.is_null() {
+            unsafe { *affected_rows = rows };
+        }
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn write_rows_with_timeout(
+    client: *const Client,
+    rows: *const *mut RowBuilder,
+    len: libc::size_t,
+    timeout_ms: u64,
+    affected_rows: *mut u32,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(client);
+        ensure_not_null!(rows);
+        let client = unsafe { &*client };
+        let mut rows = match unsafe { row_builders(rows, len) } {
+            Ok(rows) => rows,
+            Err(code) => return code as i32,
+        };
+        let rows = handle_result!(client.write_rows_with_timeout(
+            &mut rows,
+            request_timeout(client, timeout_ms),
+            &mut 0
+        ));
+        if !affected_rows.is_null() {
+            unsafe { *affected_rows = rows };
+        }
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn write_rows_with_attempts(
+    client: *const Client,
+    rows: *const *mut RowBuilder,
+    len: libc::size_t,
+    affected_rows: *mut u32,
+    attempts: *mut u32,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(client);
+        ensure_not_null!(rows);
+        let client = unsafe { &*client };
+        let mut rows = match unsafe { row_builders(rows, len) } {
+            Ok(rows) => rows,
+            Err(code) => return code as i32,
+        };
+        let mut used = 0;
+        let result = client.write_rows_with_timeout(&mut rows, client.timeout, &mut used);
+        // Attempts are reported on failures as well.
+        if !attempts.is_null() {
+            unsafe { *attempts = used };
+        }
+        let rows = handle_result!(result);
+        if !affected_rows.is_null() {
+            unsafe { *affected_rows = rows };
+        }
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn delete_rows(
+    client: *const Client,
+    rows: *const *mut RowBuilder,
+    len: libc::size_t,
+    affected_rows: *mut u32,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(client);
+        ensure_not_null!(rows);
+        let client = unsafe { &*client };
+        let mut rows = match unsafe { row_builders(rows, len) } {
+            Ok(rows) => rows,
+            Err(code) => return code as i32,
+        };
+        let rows = handle_result!(client.delete_rows(&mut rows));
+        if !affected_rows.is_null() {
+            unsafe { *affected_rows = rows };
+        }
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn delete_rows_with_timeout(
+    client: *const Client,
+    rows: *const *mut RowBuilder,
+    len: libc::size_t,
+    timeout_ms: u64,
+    affected_rows: *mut u32,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(client);
+        ensure_not_null!(rows);
+        let client = unsafe { &*client };
+        let mut rows = match unsafe { row_builders(rows, len) } {
+            Ok(rows) => rows,
+            Err(code) => return code as i32,
+        };
+        let rows = handle_result!(client.delete_rows_with_timeout(
+            &mut rows,
+            request_timeout(client, timeout_ms),
+            &mut 0
+        ));
+        if !affected_rows.is_null() {
+            unsafe { *affected_rows = rows };
+        }
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn write_row_async(
+    client: *const Client,
+    row: *mut RowBuilder,
+    callback: Option<WriteCallback>,
+    user_data: *mut libc::c_void,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(client);
+        ensure_not_null!(row);
+        if callback.is_none() {
+            return fail(StatusCode::InvalidPointer, "callback ptr cannot be null");
+        }
+        let client = unsafe { &*client };
+        let row = unsafe { &mut *row };
+        let completion = WriteCompletion {
+            callback,
+            user_data,
+        };
+        handle_result!(client.write_rows_async(&mut [row], move |res| completion.complete(res)));
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn write_rows_async(
+    client: *const Client,
+    rows: *const *mut RowBuilder,
+    len: libc::size_t,
+    callback: Option<WriteCallback>,
+    user_data: *mut libc::c_void,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(client);
+        ensure_not_null!(rows);
+        if callback.is_none() {
+            return fail(StatusCode::InvalidPointer, "callback ptr cannot be null");
+        }
+        let client = unsafe { &*client };
+        let mut rows = match unsafe { row_builders(rows, len) } {
+            Ok(rows) => rows,
+            Err(code) => return code as i32,
+        };
+        let completion = WriteCompletion {
+            callback,
+            user_data,
+        };
+        handle_result!(client.write_rows_async(&mut rows, move |res| completion.complete(res)));
+        StatusCode::Success as i32
+    })
+}
+
+/// Returns the timeout of a single request, 0 means the timeout of the client.
+fn request_timeout(client: &Client, timeout_ms: u64) -> Duration {
+    match timeout_ms {
+        0 => client.timeout,
+        ms => Duration::from_millis(ms),
     }
+}
 
-    let client_ptr = unsafe { &mut *p_client_ptr };
-    if client_ptr.is_null() {
-        return StatusCode::Success as i32;
+/// Converts an array of row builder pointers, which must be non-null and distinct.
+unsafe fn row_builders<'a>(
+    rows: *const *mut RowBuilder,
+    len: libc::size_t,
+) -> Result<Vec<&'a mut RowBuilder>, StatusCode> {
+    let rows = unsafe { std::slice::from_raw_parts(rows, len) };
+    if rows.iter().any(|row| row.is_null()) {
+        fail(StatusCode::InvalidPointer, "rows cannot contain null ptr");
+        return Err(StatusCode::InvalidPointer);
     }
+    if rows.iter().collect::<HashSet<_>>().len() != rows.len() {
+        fail(
+            StatusCode::InvalidArgument,
+            "rows cannot contain the same row builder twice",
+        );
+        return Err(StatusCode::InvalidArgument);
+    }
+    Ok(rows.iter().map(|row| unsafe { &mut **row }).collect())
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn new_batch_writer(
+    client: *const Client,
+    options: *const BatchWriterOptions,
+    callback: Option<WriteCallback>,
+    user_data: *mut libc::c_void,
+    res_ptr: *mut *const BatchWriter,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(client);
+        ensure_not_null!(options);
+        ensure_not_null!(res_ptr);
+        let client = unsafe { &*client };
+        let options = unsafe { &*options };
+        let on_flush = FlushCallback {
+            callback,
+            user_data,
+        };
+        let writer = BatchWriter::new(client, options, move |res| on_flush.report(res));
+        unsafe { *res_ptr = Box::into_raw(Box::new(writer)) };
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn batch_writer_write(
+    writer: *const BatchWriter,
+    row: *mut RowBuilder,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(writer);
+        ensure_not_null!(row);
+        let writer = unsafe { &*writer };
+        let row = unsafe { &mut *row };
+        handle_result!(writer.write(row));
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn batch_writer_flush(
+    writer: *const BatchWriter,
+    affected_rows: *mut u32,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(writer);
+        let writer = unsafe { &*writer };
+        let rows = handle_result!(writer.flush());
+        if !affected_rows.is_null() {
+            unsafe { *affected_rows = rows };
+        }
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn free_batch_writer(p_writer_ptr: *mut *mut BatchWriter) -> libc::c_int {
+    catch_panic(|| {
+        if p_writer_ptr.is_null() {
+            return StatusCode::Success as i32;
+        }
 
-    let client = unsafe { &mut **client_ptr };
-    let _ = unsafe { Box::from_raw(client) };
-    *client_ptr = ptr::null_mut();
-    StatusCode::Success as i32
+        let writer_ptr = unsafe { &mut *p_writer_ptr };
+        if writer_ptr.is_null() {
+            return StatusCode::Success as i32;
+        }
+
+        let _ = unsafe { Box::from_raw(*writer_ptr) };
+        *writer_ptr = ptr::null_mut();
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn new_stream_inserter(
+    client: *const Client,
+    res_ptr: *mut *const StreamInserter,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(client);
+        ensure_not_null!(res_ptr);
+        let client = unsafe { &*client };
+        let stream = StreamInserter::new(client);
+        unsafe { *res_ptr = Box::into_raw(Box::new(stream)) };
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn stream_write(
+    stream: *mut StreamInserter,
+    row: *mut RowBuilder,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(stream);
+        ensure_not_null!(row);
+        let stream = unsafe { &mut *stream };
+        let row = unsafe { &mut *row };
+        handle_result!(stream.write(row));
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn stream_finish(
+    stream: *mut StreamInserter,
+    affected_rows: *mut u32,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(stream);
+        let stream = unsafe { &mut *stream };
+        let rows = handle_result!(stream.finish());
+        if !affected_rows.is_null() {
+            unsafe { *affected_rows = rows };
+        }
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn free_stream_inserter(
+    p_stream_ptr: *mut *mut StreamInserter,
+) -> libc::c_int {
+    catch_panic(|| {
+        if p_stream_ptr.is_null() {
+            return StatusCode::Success as i32;
+        }
+
+        let stream_ptr = unsafe { &mut *p_stream_ptr };
+        if stream_ptr.is_null() {
+            return StatusCode::Success as i32;
+        }
+
+        let _ = unsafe { Box::from_raw(*stream_ptr) };
+        *stream_ptr = ptr::null_mut();
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn new_bulk_writer(
+    client: *const Client,
+    schema: *const RowBuilder,
+    options: *const BulkWriterOptions,
+    res_ptr: *mut *const BulkWriter,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(client);
+        ensure_not_null!(schema);
+        ensure_not_null!(options);
+        ensure_not_null!(res_ptr);
+        let client = unsafe { &*client };
+        let schema = unsafe { &*schema };
+        let options = unsafe { &*options };
+        let writer = handle_result!(BulkWriter::new(client, schema, options));
+        unsafe { *res_ptr = Box::into_raw(Box::new(writer)) };
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bulk_writer_write(
+    writer: *mut BulkWriter,
+    columns: *const ColumnData,
+    column_len: libc::size_t,
+    rows: libc::size_t,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(writer);
+        ensure_not_null!(columns);
+        let writer = unsafe { &mut *writer };
+        let columns = unsafe { std::slice::from_raw_parts(columns, column_len) };
+        handle_result!(unsafe { writer.write(columns, rows) });
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bulk_writer_finish(
+    writer: *mut BulkWriter,
+    affected_rows: *mut u64,
+) -> libc::c_int {
+    catch_panic(|| {
+        ensure_not_null!(writer);
+        let writer = unsafe { &mut *writer };
+        let rows = handle_result!(writer.finish());
+        if !affected_rows.is_null() {
+            unsafe { *affected_rows = rows };
+        }
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn free_bulk_writer(p_writer_ptr: *mut *mut BulkWriter) -> libc::c_int {
+    catch_panic(|| {
+        if p_writer_ptr.is_null() {
+            return StatusCode::Success as i32;
+        }
+
+        let writer_ptr = unsafe { &mut *p_writer_ptr };
+        if writer_ptr.is_null() {
+            return StatusCode::Success as i32;
+        }
+
+        let _ = unsafe { Box::from_raw(*writer_ptr) };
+        *writer_ptr = ptr::null_mut();
+        StatusCode::Success as i32
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn free_client(p_client_ptr: *mut *mut Client) -> libc::c_int {
+    catch_panic(|| {
+        if p_client_ptr.is_null() {
+            return StatusCode::Success as i32;
+        }
+
+        let client_ptr = unsafe { &mut *p_client_ptr };
+        if client_ptr.is_null() {
+            return StatusCode::Success as i32;
+        }
+
+        let client = unsafe { &mut **client_ptr };
+        let _ = unsafe { Box::from_raw(client) };
+        *client_ptr = ptr::null_mut();
+        StatusCode::Success as i32
+    })
+}
+
+// Functions below only access the thread local last error, which cannot panic.
+
+#[unsafe(no_mangle)]
+pub extern "C" fn last_error_code() -> libc::c_int {
+    last_error::code() as i32
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn last_error_server_code() -> u32 {
+    last_error::server_code().unwrap_or(0)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn last_error_message() -> *const libc::c_char {
+    last_error::message().unwrap_or(ptr::null())
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn clear_last_error() {
+    last_error::clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    #[test]
+    fn fail_records_message_without_log_tag() {
+        let mut row = RowBuilder::new("demo".to_string());
+        let rows = [&mut row as *mut RowBuilder, std::ptr::null_mut()];
+        let res = unsafe { row_builders(rows.as_ptr(), rows.len()) };
+        assert!(matches!(res, Err(StatusCode::InvalidPointer)));
+        assert_eq!(last_error::code(), StatusCode::InvalidPointer);
+        let msg = unsafe { CStr::from_ptr(last_error::message().unwrap()) };
+        assert_eq!(msg.to_str().unwrap(), "rows cannot contain null ptr");
+    }
+
+    #[test]
+    fn zero_timeout_means_timeout_of_client() {
+        let server = crate::test_util::StubServer::start(Default::default());
+        let client = crate::test_util::new_client(&server.addr());
+        let mut row = RowBuilder::new("demo".to_string());
+        row.add_col(
+            "ts".to_string(),
+            greptimedb_ingester::api::v1::ColumnDataType::TimestampMillisecond as i32,
+            greptimedb_ingester::SemanticType::Timestamp as i32,
+        )
+        .unwrap();
+        assert_eq!(request_timeout(&client, 0), client.timeout);
+        assert_eq!(request_timeout(&client, 5), Duration::from_millis(5));
+
+        let value = [crate::row::Value {
+            timestamp_millisecond_value: 1,
+        }];
+        let rows = [&mut row as *mut RowBuilder];
+        let mut affected_rows = 0;
+        unsafe { (*rows[0]).add_row(&value).unwrap() };
+        let code = unsafe {
+            write_rows_with_timeout(&client, rows.as_ptr(), rows.len(), 0, &mut affected_rows)
+        };
+        assert_eq!(code, StatusCode::Success as i32);
+        assert_eq!(affected_rows, 1);
+
+        unsafe { (*rows[0]).add_row(&value).unwrap() };
+        let code = unsafe {
+            delete_rows_with_timeout(&client, rows.as_ptr(), rows.len(), 0, &mut affected_rows)
+        };
+        assert_eq!(code, StatusCode::Success as i32);
+        assert_eq!(affected_rows, 1);
+    }
 }
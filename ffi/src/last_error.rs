@@ -0,0 +1,104 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The last failure of FFI calls on each thread, like `errno`.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+
+use crate::error::{Error, ErrorExt, StatusCode};
+
+struct LastError {
+    code: StatusCode,
+    message: CString,
+    server_code: Option<u32>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<LastError>> = const { RefCell::new(None) };
+}
+
+/// Records a failure of the current thread, replacing the previous one.
+pub fn set(code: StatusCode, message: String, server_code: Option<u32>) {
+    // Interior nul bytes would truncate the message in C anyway.
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with_borrow_mut(|last| {
+        *last = Some(LastError {
+            code,
+            message,
+            server_code,
+        })
+    });
+}
+
+/// Records `e` as the failure of the current thread.
+pub fn set_error(e: &Error) {
+    set(e.status_code(), e.to_string(), e.server_error_code());
+}
+
+pub fn clear() {
+    LAST_ERROR.with_borrow_mut(|last| *last = None);
+}
+
+/// Returns the status code of the last failure, or `Success` if there is none.
+pub fn code() -> StatusCode {
+    LAST_ERROR.with_borrow(|last| last.as_ref().map_or(StatusCode::Success, |e| e.code))
+}
+
+/// Returns the GreptimeDB status code of the last failure, if the server sent one.
+pub fn server_code() -> Option<u32> {
+    LAST_ERROR.with_borrow(|last| last.as_ref().and_then(|e| e.server_code))
+}
+
+/// Returns the message of the last failure, which is valid until the next
+/// failure or [clear] on the current thread.
+pub fn message() -> Option<*const libc::c_char> {
+    LAST_ERROR.with_borrow(|last| last.as_ref().map(|e| CStr::as_ptr(&e.message)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::EmptyEndpointsSnafu;
+
+    #[test]
+    fn last_error_is_thread_local() {
+        clear();
+        assert_eq!(code(), StatusCode::Success);
+        assert!(message().is_none());
+
+        set_error(&EmptyEndpointsSnafu.build());
+        assert_eq!(code(), StatusCode::InvalidArgument);
+        assert_eq!(server_code(), None);
+        let msg = unsafe { CStr::from_ptr(message().unwrap()) };
+        assert!(
+            msg.to_str()
+                .unwrap()
+                .starts_with("At least one endpoint is required")
+        );
+
+        std::thread::spawn(|| assert_eq!(code(), StatusCode::Success))
+            .join()
+            .unwrap();
+
+        set(StatusCode::Unknown, "a\0b".to_string(), Some(1003));
+        assert_eq!(code(), StatusCode::Unknown);
+        assert_eq!(server_code(), Some(1003));
+        let msg = unsafe { CStr::from_ptr(message().unwrap()) };
+        assert_eq!(msg.to_str().unwrap(), "ab");
+
+        clear();
+        assert_eq!(code(), StatusCode::Success);
+    }
+}
@@ -12,26 +12,47 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::endpoint::Endpoints;
 use crate::error::set_panic_hook;
 use crate::logger::init_logger;
+use crate::options::ClientOptions;
 use crate::row::RowBuilder;
 use greptimedb_ingester::api::v1::auth_header::AuthScheme;
-use greptimedb_ingester::api::v1::{Basic, RowInsertRequest, RowInsertRequests};
-use greptimedb_ingester::database::Database;
-use snafu::ResultExt;
+use greptimedb_ingester::api::v1::greptime_request::Request;
+use greptimedb_ingester::api::v1::{
+    AffectedRows, Basic, GreptimeRequest, GreptimeResponse, RowDeleteRequest, RowDeleteRequests,
+    RowInsertRequest, RowInsertRequests, greptime_response,
+};
+use snafu::{OptionExt, ResultExt};
 use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Duration;
 use tokio::runtime::Runtime;
-use tracing::info;
+use tonic::metadata::AsciiMetadataValue;
+use tracing::{debug, info};
 
+mod bulk;
+mod column;
+mod endpoint;
 mod error;
 mod ffi;
+mod last_error;
 mod logger;
+mod options;
+mod retry;
 mod row;
+mod stream;
+#[cfg(test)]
+mod test_util;
 mod util;
+mod writer;
+
+/// Metadata key of insert hints, see `Database::insert_with_hints` of the ingester.
+const HINTS_KEY: &str = "x-greptime-hints";
 
 pub struct Client {
     runtime: Runtime,
-    client: Database,
+    endpoints: Endpoints,
+    timeout: Duration,
 }
 
 impl Drop for Client {
@@ -41,15 +62,16 @@ impl Drop for Client {
 }
 
 impl Client {
-    pub fn new(
-        database_name: String,
-        addr: String,
-        auth: Option<(String, String)>,
-    ) -> error::Result<Self> {
+    pub fn new(options: &ClientOptions) -> error::Result<Self> {
         init_logger();
         set_panic_hook();
+        options.validate()?;
 
-        let runtime = tokio::runtime::Builder::new_multi_thread()
+        let mut runtime = tokio::runtime::Builder::new_multi_thread();
+        if options.runtime_threads > 0 {
+            runtime.worker_threads(options.runtime_threads);
+        }
+        let runtime = runtime
             .enable_all()
             .thread_name_fn(|| {
                 static ATOMIC_ID: AtomicU8 = AtomicU8::new(0);
@@ -57,26 +79,678 @@ impl Client {
                 format!("gt-client-{}", id)
             })
             .build()
-            .unwrap();
+            .context(error::BuildRuntimeSnafu)?;
 
-        let client = greptimedb_ingester::client::Client::with_urls(vec![&addr]);
-        let mut client = Database::new_with_dbname(database_name, client);
-        if let Some((username, password)) = auth {
-            client.set_auth(AuthScheme::Basic(Basic { username, password }));
-        }
+        let auth = options
+            .auth
+            .clone()
+            .map(|(username, password)| AuthScheme::Basic(Basic { username, password }));
+        // Channels spawn their workers on the runtime.
+        let endpoints = {
+            let _guard = runtime.enter();
+            Endpoints::new(options, auth)?
+        };
+
+        Ok(Self {
+            runtime,
+            endpoints,
+            timeout: options.timeout(),
+        })
+    }
 
-        Ok(Self { runtime, client })
+    /// Writes rows of the builder and returns the number of affected rows.
+    pub fn write_row(&self, row: &mut RowBuilder) -> error::Result<u32> {
+        self.write_rows(&mut [row])
     }
 
-    pub fn write_row(&self, row: &mut RowBuilder) -> error::Result<()> {
-        let insert_req: RowInsertRequest = row.into();
-        let insert_reqs = RowInsertRequests {
-            inserts: vec![insert_req],
+    /// Writes rows of all builders in a single request and returns the number
+    /// of affected rows. All builders must have the same hints.
+    pub fn write_rows(&self, rows: &mut [&mut RowBuilder]) -> error::Result<u32> {
+        self.write_rows_with_timeout(rows, self.timeout, &mut 0)
+    }
+
+    /// Same as [Client::write_rows], but overrides the request timeout of the client.
+    /// `attempts` is set to the number of attempts made, including failed ones.
+    pub fn write_rows_with_timeout(
+        &self,
+        rows: &mut [&mut RowBuilder],
+        timeout: Duration,
+        attempts: &mut u32,
+    ) -> error::Result<u32> {
+        let (insert_reqs, hints) = build_insert_requests(rows)?;
+        self.runtime.block_on(insert(
+            &self.endpoints,
+            insert_reqs,
+            &hints,
+            timeout,
+            attempts,
+        ))
+    }
+
+    /// Deletes rows identified by tags and time index of all builders in a single
+    /// request and returns the number of affected rows. Hints are not sent.
+    pub fn delete_rows(&self, rows: &mut [&mut RowBuilder]) -> error::Result<u32> {
+        self.delete_rows_with_timeout(rows, self.timeout, &mut 0)
+    }
+
+    /// Same as [Client::delete_rows], but overrides the request timeout of the client.
+    /// `attempts` is set to the number of attempts made, including failed ones.
+    pub fn delete_rows_with_timeout(
+        &self,
+        rows: &mut [&mut RowBuilder],
+        timeout: Duration,
+        attempts: &mut u32,
+    ) -> error::Result<u32> {
+        for row in rows.iter() {
+            row.check_delete_columns()?;
+        }
+        let request = GreptimeRequest {
+            header: Some(self.endpoints.header.clone()),
+            request: Some(Request::RowDeletes(RowDeleteRequests {
+                deletes: rows
+                    .iter_mut()
+                    .map(|row| RowDeleteRequest::from(&mut **row))
+                    .collect(),
+            })),
         };
         self.runtime
-            .block_on(self.client.insert(insert_reqs))
-            .map_err(Box::new)
-            .context(error::InsertReqSnafu)?;
+            .block_on(delete(&self.endpoints, request, timeout, attempts))
+    }
+
+    /// Drains rows of all builders and writes them in background, `callback`
+    /// is called on a runtime thread with the result of the write.
+    ///
+    /// Builders can be reused once it returns. Errors returned by this function
+    /// are raised before the request is sent, and `callback` won't be called.
+    pub fn write_rows_async<F>(
+        &self,
+        rows: &mut [&mut RowBuilder],
+        callback: F,
+    ) -> error::Result<()>
+    where
+        F: FnOnce(error::Result<u32>) + Send + 'static,
+    {
+        let (insert_reqs, hints) = build_insert_requests(rows)?;
+        let endpoints = self.endpoints.clone();
+        let timeout = self.timeout;
+        // The write is detached, its result is reported by the callback.
+        drop(self.runtime.spawn(async move {
+            callback(insert(&endpoints, insert_reqs, &hints, timeout, &mut 0).await);
+        }));
         Ok(())
     }
 }
+
+fn build_insert_requests(
+    rows: &mut [&mut RowBuilder],
+) -> error::Result<(RowInsertRequests, Vec<(String, String)>)> {
+    let hints = row::same_hints(rows)?;
+    let insert_reqs = RowInsertRequests {
+        inserts: rows
+            .iter_mut()
+            .map(|row| RowInsertRequest::from(&mut **row))
+            .collect(),
+    };
+    Ok((insert_reqs, hints))
+}
+
+pub(crate) async fn insert(
+    endpoints: &Endpoints,
+    insert_reqs: RowInsertRequests,
+    hints: &[(String, String)],
+    timeout: Duration,
+    attempts: &mut u32,
+) -> error::Result<u32> {
+    let request = GreptimeRequest {
+        header: Some(endpoints.header.clone()),
+        request: Some(Request::RowInserts(insert_reqs)),
+    };
+    let hints = hints_metadata(hints)?;
+    let response = with_timeout(
+        timeout,
+        endpoints.call_with_retry(request, attempts, |endpoint, request| {
+            let mut request = tonic::Request::new(request);
+            if let Some(hints) = &hints {
+                request.metadata_mut().insert(HINTS_KEY, hints.clone());
+            }
+            let mut client = endpoint.client.clone();
+            async move { Ok(client.handle(request).await?.into_inner()) }
+        }),
+    )
+    .await?
+    .map_err(Box::new)
+    .context(error::InsertReqSnafu)?;
+    let affected_rows = affected_rows(response)?;
+    debug!("Write rows done, affected rows: {}", affected_rows);
+    Ok(affected_rows)
+}
+
+async fn delete(
+    endpoints: &Endpoints,
+    request: GreptimeRequest,
+    timeout: Duration,
+    attempts: &mut u32,
+) -> error::Result<u32> {
+    let response = with_timeout(
+        timeout,
+        endpoints.call_with_retry(request, attempts, |endpoint, request| {
+            let mut client = endpoint.client.clone();
+            async move { Ok(client.handle(request).await?.into_inner()) }
+        }),
+    )
+    .await?
+    .map_err(Box::new)
+    .context(error::DeleteReqSnafu)?;
+    let affected_rows = affected_rows(response)?;
+    debug!("Delete rows done, affected rows: {}", affected_rows);
+    Ok(affected_rows)
+}
+
+async fn with_timeout<T>(timeout: Duration, future: impl Future<Output = T>) -> error::Result<T> {
+    tokio::time::timeout(timeout, future)
+        .await
+        .ok()
+        .context(error::TimeoutSnafu { timeout })
+}
+
+/// Encodes hints into the metadata value understood by the server, `None` if
+/// there are no hints.
+fn hints_metadata(hints: &[(String, String)]) -> error::Result<Option<AsciiMetadataValue>> {
+    if hints.is_empty() {
+        return Ok(None);
+    }
+    let value = hints
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join(",");
+    let metadata =
+        AsciiMetadataValue::try_from(value.as_str())
+            .ok()
+            .context(error::InvalidHintSnafu {
+                key: HINTS_KEY,
+                value: &value,
+            })?;
+    Ok(Some(metadata))
+}
+
+pub(crate) fn affected_rows(response: GreptimeResponse) -> error::Result<u32> {
+    let response = response.response.context(error::IllegalResponseSnafu {
+        msg: "GreptimeResponse is empty",
+    })?;
+    let greptime_response::Response::AffectedRows(AffectedRows { value }) = response;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorExt;
+    use crate::options::TlsConfig;
+    use crate::retry::RetryPolicy;
+    use greptimedb_ingester::SemanticType;
+    use greptimedb_ingester::api::v1::ColumnDataType;
+    use std::sync::mpsc;
+    use tonic::transport::{Certificate, Identity};
+
+    #[test]
+    fn write_rows_async_reports_failure_to_callback() {
+        // Nothing listens on port 1, so the write fails once it is sent.
+        let client = test_util::new_client("127.0.0.1:1");
+        let mut builder = RowBuilder::new("demo".to_string());
+        builder
+            .add_col(
+                "ts".to_string(),
+                ColumnDataType::TimestampMillisecond as i32,
+                SemanticType::Timestamp as i32,
+            )
+            .unwrap();
+        unsafe {
+            builder
+                .add_row(&[row::Value {
+                    timestamp_millisecond_value: 1,
+                }])
+                .unwrap();
+        }
+
+        let (tx, rx) = mpsc::channel();
+        client
+            .write_rows_async(&mut [&mut builder], move |res| tx.send(res).unwrap())
+            .unwrap();
+
+        let res = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(matches!(res, Err(error::Error::InsertReq { .. })));
+        assert_eq!(
+            res.unwrap_err().status_code(),
+            error::StatusCode::ServerUnavailable
+        );
+    }
+
+    #[test]
+    fn delete_rows_rejects_field_columns() {
+        let client = test_util::new_client("127.0.0.1:1");
+        let mut builder = RowBuilder::new("demo".to_string());
+        builder
+            .add_col(
+                "ts".to_string(),
+                ColumnDataType::TimestampMillisecond as i32,
+                SemanticType::Timestamp as i32,
+            )
+            .unwrap();
+        builder
+            .add_col(
+                "v".to_string(),
+                ColumnDataType::Int64 as i32,
+                SemanticType::Field as i32,
+            )
+            .unwrap();
+        unsafe {
+            builder
+                .add_row(&[
+                    row::Value {
+                        timestamp_millisecond_value: 1,
+                    },
+                    row::Value { i64_value: 1 },
+                ])
+                .unwrap();
+        }
+
+        let err = client.delete_rows(&mut [&mut builder]).unwrap_err();
+        assert!(matches!(err, error::Error::InvalidDeleteColumn { .. }));
+        // rows are kept so that the builder can be fixed and retried.
+        let req: RowDeleteRequest = (&mut builder).into();
+        assert_eq!(req.rows.unwrap().rows.len(), 1);
+    }
+
+    /// Writes a CA certificate and a client certificate with its key issued by
+    /// the CA into `dir`, and returns the CA and a server identity for `names`.
+    fn write_tls_files(dir: &std::path::Path, names: &[&str]) -> (Certificate, Identity) {
+        let mut ca_params = rcgen::CertificateParams::default();
+        ca_params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        let ca =
+            rcgen::CertifiedIssuer::self_signed(ca_params, rcgen::KeyPair::generate().unwrap())
+                .unwrap();
+        let issue = |names: &[&str]| {
+            let key = rcgen::KeyPair::generate().unwrap();
+            let names: Vec<_> = names.iter().map(|name| name.to_string()).collect();
+            let cert = rcgen::CertificateParams::new(names)
+                .unwrap()
+                .signed_by(&key, &ca)
+                .unwrap();
+            (cert.pem(), key.serialize_pem())
+        };
+
+        let (client_cert, client_key) = issue(&["client"]);
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+        std::fs::write(dir.join("client.pem"), client_cert).unwrap();
+        std::fs::write(dir.join("client-key.pem"), client_key).unwrap();
+        let (server_cert, server_key) = issue(names);
+        (
+            Certificate::from_pem(ca.pem()),
+            Identity::from_pem(server_cert, server_key),
+        )
+    }
+
+    fn tls_config(
+        dir: &std::path::Path,
+        client_identity: bool,
+        server_name: Option<&str>,
+    ) -> TlsConfig {
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+        TlsConfig {
+            ca_cert_path: path("ca.pem"),
+            client_identity: client_identity.then(|| (path("client.pem"), path("client-key.pem"))),
+            server_name: server_name.map(str::to_string),
+        }
+    }
+
+    fn write_over_tls(server: &test_util::StubServer, tls: TlsConfig) -> error::Result<u32> {
+        let mut options = ClientOptions::new("public".to_string());
+        options.add_endpoint(server.addr());
+        options.set_tls(tls);
+        let client = Client::new(&options)?;
+        let mut builder = RowBuilder::new("demo".to_string());
+        builder
+            .add_col(
+                "ts".to_string(),
+                ColumnDataType::TimestampMillisecond as i32,
+                SemanticType::Timestamp as i32,
+            )
+            .unwrap();
+        unsafe {
+            builder
+                .add_row(&[row::Value {
+                    timestamp_millisecond_value: 1,
+                }])
+                .unwrap();
+        }
+        client.write_row(&mut builder)
+    }
+
+    fn tls_dir(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("greptime-tls-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn write_rows_over_tls() {
+        let dir = tls_dir("ca");
+        let (_, identity) = write_tls_files(&dir, &["127.0.0.1"]);
+        let server = test_util::StubServer::start(test_util::StubOptions {
+            identity: Some(identity),
+            ..Default::default()
+        });
+
+        // Only the CA is required if the server does not verify clients.
+        assert_eq!(
+            write_over_tls(&server, tls_config(&dir, false, None)).unwrap(),
+            1
+        );
+        assert_eq!(
+            write_over_tls(&server, tls_config(&dir, true, None)).unwrap(),
+            1
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn write_rows_over_mutual_tls() {
+        let dir = tls_dir("mutual");
+        let (ca, identity) = write_tls_files(&dir, &["127.0.0.1"]);
+        let server = test_util::StubServer::start(test_util::StubOptions {
+            identity: Some(identity),
+            client_ca: Some(ca),
+            ..Default::default()
+        });
+
+        assert_eq!(
+            write_over_tls(&server, tls_config(&dir, true, None)).unwrap(),
+            1
+        );
+        let err = write_over_tls(&server, tls_config(&dir, false, None)).unwrap_err();
+        assert!(matches!(err, error::Error::InsertReq { .. }));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn write_rows_over_tls_with_server_name() {
+        let dir = tls_dir("server-name");
+        // The certificate is not valid for the address of the endpoint.
+        let (_, identity) = write_tls_files(&dir, &["greptime.test"]);
+        let server = test_util::StubServer::start(test_util::StubOptions {
+            identity: Some(identity),
+            ..Default::default()
+        });
+
+        let err = write_over_tls(&server, tls_config(&dir, false, None)).unwrap_err();
+        assert!(matches!(err, error::Error::InsertReq { .. }));
+        let tls = tls_config(&dir, false, Some("greptime.test"));
+        assert_eq!(write_over_tls(&server, tls).unwrap(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn new_client_rejects_missing_tls_files() {
+        let dir = tls_dir("missing");
+        let mut options = ClientOptions::new("public".to_string());
+        options.add_endpoint("127.0.0.1:1".to_string());
+        options.set_tls(tls_config(&dir, true, None));
+        let err = Client::new(&options).err().unwrap();
+        assert!(matches!(err, error::Error::ReadTlsFile { .. }));
+    }
+
+    #[test]
+    fn new_client_requires_endpoints() {
+        let options = ClientOptions::new("public".to_string());
+        let err = Client::new(&options).err().unwrap();
+        assert!(matches!(err, error::Error::EmptyEndpoints { .. }));
+    }
+
+    #[test]
+    fn write_rows_times_out() {
+        let server = test_util::StubServer::start(test_util::StubOptions {
+            delay: Duration::from_millis(500),
+            ..Default::default()
+        });
+        let mut options = ClientOptions::new("public".to_string());
+        options.add_endpoint(server.addr());
+        options.set_timeouts(None, Some(Duration::from_millis(50)));
+        let client = Client::new(&options).unwrap();
+        let mut builder = RowBuilder::new("demo".to_string());
+        builder
+            .add_col(
+                "ts".to_string(),
+                ColumnDataType::TimestampMillisecond as i32,
+                SemanticType::Timestamp as i32,
+            )
+            .unwrap();
+        let values = [row::Value {
+            timestamp_millisecond_value: 1,
+        }];
+
+        unsafe { builder.add_row(&values).unwrap() };
+        let err = client.write_row(&mut builder).unwrap_err();
+        assert_eq!(err.status_code(), error::StatusCode::Timeout);
+
+        // A longer per-call timeout overrides the client one.
+        unsafe { builder.add_row(&values).unwrap() };
+        let affected_rows = client
+            .write_rows_with_timeout(&mut [&mut builder], Duration::from_secs(5), &mut 0)
+            .unwrap();
+        assert_eq!(affected_rows, 1);
+    }
+
+    fn retry_client(failures: Vec<tonic::Code>) -> (test_util::StubServer, Client) {
+        let server = test_util::StubServer::start(test_util::StubOptions {
+            failures,
+            ..Default::default()
+        });
+        let mut options = ClientOptions::new("public".to_string());
+        options.add_endpoint(server.addr());
+        options.set_retry(RetryPolicy::new(3, 10, 100, true));
+        let client = Client::new(&options).unwrap();
+        (server, client)
+    }
+
+    fn ts_builder() -> RowBuilder {
+        let mut builder = RowBuilder::new("demo".to_string());
+        builder
+            .add_col(
+                "ts".to_string(),
+                ColumnDataType::TimestampMillisecond as i32,
+                SemanticType::Timestamp as i32,
+            )
+            .unwrap();
+        let values = [row::Value {
+            timestamp_millisecond_value: 1,
+        }];
+        unsafe { builder.add_row(&values).unwrap() };
+        builder
+    }
+
+    #[test]
+    fn write_rows_retries_transient_errors() {
+        let (_server, client) = retry_client(vec![
+            tonic::Code::Unavailable,
+            tonic::Code::DeadlineExceeded,
+        ]);
+        let mut attempts = 0;
+        let affected_rows = client
+            .write_rows_with_timeout(&mut [&mut ts_builder()], client.timeout, &mut attempts)
+            .unwrap();
+        assert_eq!(affected_rows, 1);
+        assert_eq!(attempts, 3);
+
+        // Gives up after max attempts.
+        let (_server, client) = retry_client(vec![tonic::Code::Unavailable; 3]);
+        let err = client
+            .write_rows_with_timeout(&mut [&mut ts_builder()], client.timeout, &mut attempts)
+            .unwrap_err();
+        assert_eq!(err.status_code(), error::StatusCode::ServerUnavailable);
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn write_rows_does_not_retry_schema_errors() {
+        let (_server, client) = retry_client(vec![tonic::Code::InvalidArgument]);
+        let mut attempts = 0;
+        client
+            .write_rows_with_timeout(&mut [&mut ts_builder()], client.timeout, &mut attempts)
+            .unwrap_err();
+        assert_eq!(attempts, 1);
+
+        // The next request succeeds, so the failure was not consumed by a retry.
+        let mut attempts = 0;
+        let affected_rows = client
+            .delete_rows_with_timeout(&mut [&mut ts_builder()], client.timeout, &mut attempts)
+            .unwrap();
+        assert_eq!(affected_rows, 1);
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn write_rows_sends_hints() {
+        let server = test_util::StubServer::start(Default::default());
+        let client = test_util::new_client(&server.addr());
+        let mut builder = ts_builder();
+        builder
+            .set_hint("ttl".to_string(), "7d".to_string())
+            .unwrap();
+        builder
+            .set_hint("append_mode".to_string(), "true".to_string())
+            .unwrap();
+
+        assert_eq!(client.write_rows(&mut [&mut builder]).unwrap(), 1);
+        client.write_rows(&mut [&mut ts_builder()]).unwrap();
+
+        let received = server.received();
+        assert!(
+            received
+                .iter()
+                .all(|r| matches!(r.request.request, Some(Request::RowInserts(_))))
+        );
+        let hints: Vec<_> = received.into_iter().map(|r| r.hints).collect();
+        assert_eq!(hints, [Some("ttl=7d,append_mode=true".to_string()), None]);
+    }
+
+    #[test]
+    fn write_rows_rejects_tables_with_different_hints() {
+        let server = test_util::StubServer::start(Default::default());
+        let client = test_util::new_client(&server.addr());
+        let mut a = ts_builder();
+        a.set_hint("ttl".to_string(), "7d".to_string()).unwrap();
+        let mut b = ts_builder();
+        b.set_hint("append_mode".to_string(), "true".to_string())
+            .unwrap();
+
+        let err = client.write_rows(&mut [&mut a, &mut b]).unwrap_err();
+        assert!(matches!(err, error::Error::MismatchedHints { .. }));
+        assert_eq!(err.status_code(), error::StatusCode::InvalidArgument);
+        assert!(server.received().is_empty());
+        // Rows are kept, so the tables can be written separately.
+        assert_eq!(client.write_rows(&mut [&mut a]).unwrap(), 1);
+        assert_eq!(client.write_rows(&mut [&mut b]).unwrap(), 1);
+
+        let hints: Vec<_> = server.received().into_iter().map(|r| r.hints).collect();
+        assert_eq!(
+            hints,
+            [
+                Some("ttl=7d".to_string()),
+                Some("append_mode=true".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn write_rows_async_reports_affected_rows_once() {
+        let server = test_util::StubServer::start(Default::default());
+        let client = test_util::new_client(&server.addr());
+        let mut a = ts_builder();
+        let mut b = ts_builder();
+        unsafe {
+            b.add_row(&[row::Value {
+                timestamp_millisecond_value: 2,
+            }])
+            .unwrap()
+        };
+
+        let (tx, rx) = mpsc::channel();
+        client
+            .write_rows_async(&mut [&mut a, &mut b], move |res| tx.send(res).unwrap())
+            .unwrap();
+        // Rows are drained before the write completes.
+        for builder in [&mut a, &mut b] {
+            let req: RowInsertRequest = builder.into();
+            assert!(req.rows.unwrap().rows.is_empty());
+        }
+
+        let res = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(res.unwrap(), 3);
+        // The callback is dropped once called, which disconnects the channel.
+        assert!(matches!(
+            rx.recv_timeout(Duration::from_secs(1)),
+            Err(mpsc::RecvTimeoutError::Disconnected)
+        ));
+        assert_eq!(server.received().len(), 1);
+    }
+
+    #[test]
+    fn delete_rows_sends_tags_and_time_index() {
+        let server = test_util::StubServer::start(Default::default());
+        let client = test_util::new_client(&server.addr());
+        let mut builder = RowBuilder::new("demo".to_string());
+        builder
+            .add_col(
+                "host".to_string(),
+                ColumnDataType::String as i32,
+                SemanticType::Tag as i32,
+            )
+            .unwrap();
+        builder
+            .add_col(
+                "ts".to_string(),
+                ColumnDataType::TimestampMillisecond as i32,
+                SemanticType::Timestamp as i32,
+            )
+            .unwrap();
+        for ts in [1, 2] {
+            let values = [
+                row::Value {
+                    string_value: c"host1".as_ptr(),
+                },
+                row::Value {
+                    timestamp_millisecond_value: ts,
+                },
+            ];
+            unsafe { builder.add_row(&values).unwrap() };
+        }
+
+        assert_eq!(client.delete_rows(&mut [&mut builder]).unwrap(), 2);
+
+        let received = server.received();
+        assert_eq!(received.len(), 1);
+        let Some(Request::RowDeletes(deletes)) = &received[0].request.request else {
+            panic!("expect RowDeletes, got {:?}", received[0].request);
+        };
+        assert_eq!(deletes.deletes.len(), 1);
+        assert_eq!(deletes.deletes[0].table_name, "demo");
+        let rows = deletes.deletes[0].rows.as_ref().unwrap();
+        let columns: Vec<_> = rows
+            .schema
+            .iter()
+            .map(|col| (col.column_name.as_str(), col.semantic_type))
+            .collect();
+        assert_eq!(
+            columns,
+            [
+                ("host", SemanticType::Tag as i32),
+                ("ts", SemanticType::Timestamp as i32)
+            ]
+        );
+        assert_eq!(rows.rows.len(), 2);
+        assert!(rows.rows.iter().all(|row| row.values.len() == 2));
+    }
+}
@@ -12,98 +12,419 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use lazy_static::lazy_static;
+use std::sync::Mutex;
+
 use serde_derive::{Deserialize, Serialize};
+use snafu::{ResultExt, ensure};
+use tracing::Dispatch;
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
-use tracing_bunyan_formatter::JsonStorageLayer;
+use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
 use tracing_subscriber::fmt::Layer;
+use tracing_subscriber::fmt::MakeWriter;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::{Registry, filter};
 
-lazy_static! {
-    static ref LOG_GUARDS: Vec<WorkerGuard> = init_logger_inner();
+use crate::error;
+use crate::util::convert_c_string;
+
+const APP_NAME: &str = "greptimedb-client-ffi";
+
+/// Guards flushing the non-blocking writers, `None` until logging is initialized.
+static LOG_GUARDS: Mutex<Option<Vec<WorkerGuard>>> = Mutex::new(None);
+
+type BoxedLayer = Box<dyn tracing_subscriber::Layer<Registry> + Send + Sync>;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogRotation {
+    #[default]
+    Hourly,
+    Daily,
+    Minutely,
+    Never,
+}
+
+impl From<LogRotation> for Rotation {
+    fn from(rotation: LogRotation) -> Self {
+        match rotation {
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Minutely => Rotation::MINUTELY,
+            LogRotation::Never => Rotation::NEVER,
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(default)]
-pub struct LoggingOptions {
+pub struct LoggingConfig {
     pub dir: String,
     pub level: Option<String>,
+    pub format: LogFormat,
+    pub rotation: LogRotation,
+    /// Writes logs to files in `dir`.
+    pub file: bool,
+    pub stdout: bool,
 }
 
-impl Default for LoggingOptions {
+impl Default for LoggingConfig {
     fn default() -> Self {
         Self {
             dir: "/tmp/greptimedb-client/logs".to_string(),
             level: None,
+            format: LogFormat::Text,
+            rotation: LogRotation::Hourly,
+            file: true,
+            stdout: true,
         }
     }
 }
 
-pub fn init_logger() {
-    LOG_GUARDS.len();
+/// Logging options from C, the layout is `LoggingOptions` in `greptime.h`.
+#[repr(C)]
+pub struct LoggingOptions {
+    dir: *const libc::c_char,
+    level: *const libc::c_char,
+    format: i32,
+    rotation: i32,
+    disable_file: bool,
+    disable_stdout: bool,
 }
 
-#[allow(clippy::print_stdout)]
-pub fn init_logger_inner() -> Vec<WorkerGuard> {
-    let app_name = "greptimedb-client-ffi".to_string();
-    let opts = LoggingOptions::default();
+impl LoggingOptions {
+    /// Null strings keep the defaults.
+    pub unsafe fn to_logging_config(&self) -> error::Result<LoggingConfig> {
+        let default = LoggingConfig::default();
+        let dir = if self.dir.is_null() {
+            default.dir
+        } else {
+            convert_c_string(self.dir)?
+        };
+        let level = if self.level.is_null() {
+            None
+        } else {
+            Some(convert_c_string(self.level)?)
+        };
+        let format = match self.format {
+            0 => LogFormat::Text,
+            1 => LogFormat::Json,
+            format => {
+                return error::InvalidLoggingOptionsSnafu {
+                    reason: format!("unknown format: {format}"),
+                }
+                .fail();
+            }
+        };
+        let rotation = match self.rotation {
+            0 => LogRotation::Hourly,
+            1 => LogRotation::Daily,
+            2 => LogRotation::Minutely,
+            3 => LogRotation::Never,
+            rotation => {
+                return error::InvalidLoggingOptionsSnafu {
+                    reason: format!("unknown rotation: {rotation}"),
+                }
+                .fail();
+            }
+        };
+        Ok(LoggingConfig {
+            dir,
+            level,
+            format,
+            rotation,
+            file: !self.disable_file,
+            stdout: !self.disable_stdout,
+        })
+    }
+}
 
-    let mut guards = vec![];
-    let dir = &opts.dir;
-    let level = &opts.level;
+/// Initializes logging with default options, unless it is initialized.
+#[allow(clippy::print_stderr)]
+pub fn init_logger() {
+    let mut guards = LOG_GUARDS.lock().unwrap();
+    if guards.is_none() {
+        let res = init_logger_inner(&LoggingConfig::default());
+        // Logs of the host application's subscriber, if any, are kept.
+        *guards = Some(res.unwrap_or_else(|e| {
+            eprintln!("Failed to initialize logging of {APP_NAME}: {e}");
+            vec![]
+        }));
+    }
+}
 
-    // Enable log compatible layer to convert log record to tracing span.
-    LogTracer::init().expect("log tracer must be valid");
+/// Initializes logging with `opts`, which fails if it is initialized, either
+/// explicitly or by creating a client.
+pub fn init_logger_with_options(opts: &LoggingConfig) -> error::Result<()> {
+    let mut guards = LOG_GUARDS.lock().unwrap();
+    ensure!(guards.is_none(), error::LoggerInitializedSnafu);
+    *guards = Some(init_logger_inner(opts)?);
+    Ok(())
+}
 
-    // Stdout layer.
-    let (stdout_writer, stdout_guard) = tracing_appender::non_blocking(std::io::stdout());
-    let stdout_logging_layer = Layer::new().with_writer(stdout_writer);
-    guards.push(stdout_guard);
-
-    // JSON log layer.
-    let rolling_appender = RollingFileAppender::new(Rotation::HOURLY, dir, app_name.clone());
-    let (rolling_writer, rolling_writer_guard) = tracing_appender::non_blocking(rolling_appender);
-    let file_logging_layer = tracing_subscriber::fmt::layer()
-        .pretty()
-        .with_writer(rolling_writer);
-    guards.push(rolling_writer_guard);
-
-    // error JSON log layer.
-    let err_rolling_appender =
-        RollingFileAppender::new(Rotation::HOURLY, dir, format!("{}-{}", app_name, "err"));
-    let (err_rolling_writer, err_rolling_writer_guard) =
-        tracing_appender::non_blocking(err_rolling_appender);
-
-    let err_file_logging_layer = tracing_subscriber::fmt::layer()
-        .pretty()
-        .with_writer(err_rolling_writer);
-    guards.push(err_rolling_writer_guard);
+fn init_logger_inner(opts: &LoggingConfig) -> error::Result<Vec<WorkerGuard>> {
+    let Some((dispatch, guards)) = new_dispatch(opts)? else {
+        // Without any output, events go to the subscriber of the host application, if any.
+        return Ok(vec![]);
+    };
+    tracing::dispatcher::set_global_default(dispatch).context(error::SetGlobalSubscriberSnafu)?;
+    // Converts log records to tracing events, unless the host application
+    // has installed a logger.
+    let _ = LogTracer::init();
 
+    Ok(guards)
+}
+
+/// Builds the subscriber of `opts` without installing it, `None` if all
+/// outputs are disabled.
+fn new_dispatch(opts: &LoggingConfig) -> error::Result<Option<(Dispatch, Vec<WorkerGuard>)>> {
     // resolve log level settings from:
-    // - options from command line or config files
-    // - environment variable: RUST_LOG
+    // - options
+    // - environment variable: GT_LOG_LEVEL
     // - default settings
     let rust_log_env = std::env::var("GT_LOG_LEVEL").ok();
-    let targets_string = level
+    let targets_string = opts
+        .level
         .as_deref()
         .or(rust_log_env.as_deref())
         .unwrap_or("info");
-    let filter = targets_string
-        .parse::<filter::Targets>()
-        .expect("error parsing log level string");
-
-    let subscriber = Registry::default()
-        .with(filter)
-        .with(JsonStorageLayer)
-        .with(stdout_logging_layer)
-        .with(file_logging_layer)
-        .with(err_file_logging_layer.with_filter(filter::LevelFilter::ERROR));
-    tracing::subscriber::set_global_default(subscriber)
-        .expect("error setting global tracing subscriber");
-
-    guards
+    let filter =
+        targets_string
+            .parse::<filter::Targets>()
+            .context(error::InvalidLogLevelSnafu {
+                level: targets_string,
+            })?;
+
+    let mut guards = vec![];
+    let mut layers: Vec<BoxedLayer> = vec![];
+
+    // Stdout layer.
+    if opts.stdout {
+        let (stdout_writer, stdout_guard) = tracing_appender::non_blocking(std::io::stdout());
+        layers.push(format_layer(opts.format, stdout_writer, false));
+        guards.push(stdout_guard);
+    }
+
+    if opts.file {
+        // Log layer.
+        let rolling_appender = new_rolling_appender(opts, APP_NAME.to_string())?;
+        let (rolling_writer, rolling_writer_guard) =
+            tracing_appender::non_blocking(rolling_appender);
+        layers.push(format_layer(opts.format, rolling_writer, true));
+        guards.push(rolling_writer_guard);
+
+        // Error log layer.
+        let err_rolling_appender = new_rolling_appender(opts, format!("{}-{}", APP_NAME, "err"))?;
+        let (err_rolling_writer, err_rolling_writer_guard) =
+            tracing_appender::non_blocking(err_rolling_appender);
+        let err_layer = format_layer(opts.format, err_rolling_writer, true)
+            .with_filter(filter::LevelFilter::ERROR);
+        layers.push(Box::new(err_layer));
+        guards.push(err_rolling_writer_guard);
+    }
+
+    if layers.is_empty() {
+        return Ok(None);
+    }
+    if opts.format == LogFormat::Json {
+        // Must precede the bunyan layers to store fields of spans.
+        layers.insert(0, Box::new(JsonStorageLayer));
+    }
+
+    let subscriber = Registry::default().with(layers).with(filter);
+    Ok(Some((Dispatch::new(subscriber), guards)))
+}
+
+fn new_rolling_appender(
+    opts: &LoggingConfig,
+    prefix: String,
+) -> error::Result<RollingFileAppender> {
+    RollingFileAppender::builder()
+        .rotation(opts.rotation.into())
+        .filename_prefix(prefix)
+        .build(&opts.dir)
+        .context(error::CreateLogFileSnafu { dir: &opts.dir })
+}
+
+fn format_layer<W>(format: LogFormat, writer: W, pretty: bool) -> BoxedLayer
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    match format {
+        LogFormat::Json => Box::new(BunyanFormattingLayer::new(APP_NAME.to_string(), writer)),
+        LogFormat::Text if pretty => Box::new(Layer::new().pretty().with_writer(writer)),
+        LogFormat::Text => Box::new(Layer::new().with_writer(writer)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ErrorExt, StatusCode};
+    use std::path::{Path, PathBuf};
+    use std::ptr;
+
+    #[test]
+    fn logging_options_from_c() {
+        let mut options = LoggingOptions {
+            dir: ptr::null(),
+            level: c"debug".as_ptr(),
+            format: 1,
+            rotation: 3,
+            disable_file: true,
+            disable_stdout: false,
+        };
+        let opts = unsafe { options.to_logging_config().unwrap() };
+        assert_eq!(opts.dir, LoggingConfig::default().dir);
+        assert_eq!(opts.level.as_deref(), Some("debug"));
+        assert_eq!(opts.format, LogFormat::Json);
+        assert_eq!(opts.rotation, LogRotation::Never);
+        assert!(!opts.file && opts.stdout);
+
+        options.format = 2;
+        let err = unsafe { options.to_logging_config().unwrap_err() };
+        assert_eq!(err.status_code(), StatusCode::InvalidArgument);
+    }
+
+    fn log_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("greptime-logs-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// Logs an info and an error event with a file-only subscriber of `opts`,
+    /// which is only installed on the current thread.
+    fn log_to_files(opts: LoggingConfig) {
+        let opts = LoggingConfig {
+            stdout: false,
+            ..opts
+        };
+        let (dispatch, guards) = new_dispatch(&opts).unwrap().unwrap();
+        tracing::dispatcher::with_default(&dispatch, || {
+            tracing::info!("hello");
+            tracing::error!("oops");
+        });
+        // Flushes the non-blocking writers.
+        drop(guards);
+    }
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn logs_to_files_in_dir() {
+        let dir = log_dir("text");
+        log_to_files(LoggingConfig {
+            dir: dir.to_str().unwrap().to_string(),
+            rotation: LogRotation::Never,
+            ..Default::default()
+        });
+
+        // Files are not rotated, so they are named by the prefixes only.
+        assert_eq!(file_names(&dir), [APP_NAME, &format!("{APP_NAME}-err")]);
+        let logs = std::fs::read_to_string(dir.join(APP_NAME)).unwrap();
+        assert!(logs.contains("hello") && logs.contains("oops"));
+        let err_logs = std::fs::read_to_string(dir.join(format!("{APP_NAME}-err"))).unwrap();
+        assert!(!err_logs.contains("hello") && err_logs.contains("oops"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn logs_json_lines() {
+        let dir = log_dir("json");
+        log_to_files(LoggingConfig {
+            dir: dir.to_str().unwrap().to_string(),
+            format: LogFormat::Json,
+            rotation: LogRotation::Never,
+            ..Default::default()
+        });
+
+        let logs = std::fs::read_to_string(dir.join(APP_NAME)).unwrap();
+        let messages: Vec<_> = logs
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["msg"].clone())
+            .collect();
+        assert_eq!(messages, ["hello", "oops"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotated_files_are_suffixed_by_date() {
+        let dir = log_dir("daily");
+        log_to_files(LoggingConfig {
+            dir: dir.to_str().unwrap().to_string(),
+            rotation: LogRotation::Daily,
+            ..Default::default()
+        });
+
+        // Like greptimedb-client-ffi.2023-11-20.
+        let names = file_names(&dir);
+        assert_eq!(names.len(), 2);
+        for (name, prefix) in names
+            .iter()
+            .zip([format!("{APP_NAME}-err"), APP_NAME.to_string()])
+        {
+            let date = name.strip_prefix(&format!("{prefix}.")).unwrap();
+            assert_eq!(date.len(), "yyyy-mm-dd".len());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn disabled_outputs_are_not_built() {
+        let dir = log_dir("disabled");
+        let opts = LoggingConfig {
+            dir: dir.to_str().unwrap().to_string(),
+            file: false,
+            stdout: false,
+            ..Default::default()
+        };
+        assert!(new_dispatch(&opts).unwrap().is_none());
+        assert!(init_logger_inner(&opts).unwrap().is_empty());
+
+        // Only the stdout writer is built without files.
+        let opts = LoggingConfig {
+            stdout: true,
+            ..opts
+        };
+        let (_, guards) = new_dispatch(&opts).unwrap().unwrap();
+        assert_eq!(guards.len(), 1);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn invalid_logging_options_are_rejected() {
+        let opts = LoggingConfig {
+            level: Some("greptime=loud".to_string()),
+            ..Default::default()
+        };
+        let err = new_dispatch(&opts).unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::InvalidArgument);
+
+        let file = log_dir("not-a-dir");
+        std::fs::write(&file, "").unwrap();
+        let opts = LoggingConfig {
+            dir: file.to_str().unwrap().to_string(),
+            stdout: false,
+            ..Default::default()
+        };
+        let err = new_dispatch(&opts).unwrap_err();
+        assert!(matches!(err, error::Error::CreateLogFile { .. }));
+        std::fs::remove_file(&file).unwrap();
+    }
 }
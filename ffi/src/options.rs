@@ -0,0 +1,245 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use crate::error;
+use crate::retry::RetryPolicy;
+use crate::util::convert_c_string;
+use greptimedb_ingester::channel_manager::DEFAULT_GRPC_REQUEST_TIMEOUT_SECS;
+use greptimedb_ingester::{ChannelConfig, ClientTlsOption};
+use snafu::{ResultExt, ensure};
+use tonic::transport::{Certificate, ClientTlsConfig, Identity};
+
+/// TLS options from C, paths are of PEM files.
+#[repr(C)]
+pub struct TlsOptions {
+    ca_cert_path: *const libc::c_char,
+    client_cert_path: *const libc::c_char,
+    client_key_path: *const libc::c_char,
+    server_name: *const libc::c_char,
+}
+
+impl TlsOptions {
+    pub fn to_tls_config(&self) -> error::Result<TlsConfig> {
+        let optional = |s: *const libc::c_char| {
+            if s.is_null() {
+                Ok(None)
+            } else {
+                convert_c_string(s).map(Some)
+            }
+        };
+        let client_identity = match (
+            optional(self.client_cert_path)?,
+            optional(self.client_key_path)?,
+        ) {
+            (Some(cert_path), Some(key_path)) => Some((cert_path, key_path)),
+            (None, None) => None,
+            _ => {
+                return error::InvalidTlsOptionsSnafu {
+                    reason: "client certificate and key must be given together",
+                }
+                .fail();
+            }
+        };
+        Ok(TlsConfig {
+            ca_cert_path: convert_c_string(self.ca_cert_path)?,
+            client_identity,
+            server_name: optional(self.server_name)?,
+        })
+    }
+}
+
+/// TLS settings of connections to endpoints.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// CA certificates to verify servers.
+    pub ca_cert_path: String,
+    /// Paths of the client certificate and key for mutual TLS.
+    pub client_identity: Option<(String, String)>,
+    /// Name to verify server certificates against, instead of the endpoint host.
+    pub server_name: Option<String>,
+}
+
+impl TlsConfig {
+    pub(crate) fn to_client_tls_config(&self) -> error::Result<ClientTlsConfig> {
+        let mut config = ClientTlsConfig::new()
+            .ca_certificate(Certificate::from_pem(read_tls_file(&self.ca_cert_path)?));
+        if let Some((cert_path, key_path)) = &self.client_identity {
+            config = config.identity(Identity::from_pem(
+                read_tls_file(cert_path)?,
+                read_tls_file(key_path)?,
+            ));
+        }
+        if let Some(server_name) = &self.server_name {
+            config = config.domain_name(server_name);
+        }
+        Ok(config)
+    }
+
+    /// Returns the TLS option of the ingester, which only supports mutual TLS
+    /// verifying the endpoint host.
+    pub(crate) fn to_client_tls_option(&self) -> Option<ClientTlsOption> {
+        let (cert_path, key_path) = self.client_identity.clone()?;
+        if self.server_name.is_some() {
+            return None;
+        }
+        Some(ClientTlsOption {
+            server_ca_cert_path: self.ca_cert_path.clone(),
+            client_cert_path: cert_path,
+            client_key_path: key_path,
+        })
+    }
+}
+
+fn read_tls_file(path: &str) -> error::Result<Vec<u8>> {
+    std::fs::read(path).context(error::ReadTlsFileSnafu { path })
+}
+
+/// Options to create a [Client](crate::Client).
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    pub(crate) database_name: String,
+    pub(crate) endpoints: Vec<String>,
+    pub(crate) auth: Option<(String, String)>,
+    tls: Option<TlsConfig>,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    compression: bool,
+    pub(crate) runtime_threads: usize,
+    pub(crate) retry: RetryPolicy,
+}
+
+impl ClientOptions {
+    pub fn new(database_name: String) -> Self {
+        Self {
+            database_name,
+            endpoints: vec![],
+            auth: None,
+            tls: None,
+            connect_timeout: None,
+            timeout: None,
+            compression: false,
+            runtime_threads: 0,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    pub fn add_endpoint(&mut self, endpoint: String) {
+        self.endpoints.push(endpoint);
+    }
+
+    pub fn set_auth(&mut self, auth: Option<(String, String)>) {
+        self.auth = auth;
+    }
+
+    pub fn set_tls(&mut self, tls: TlsConfig) {
+        self.tls = Some(tls);
+    }
+
+    /// Sets timeouts of connecting and of every request, `None` keeps the default.
+    /// The request timeout covers retries on other endpoints.
+    pub fn set_timeouts(&mut self, connect_timeout: Option<Duration>, timeout: Option<Duration>) {
+        self.connect_timeout = connect_timeout;
+        self.timeout = timeout;
+    }
+
+    /// Sets compression of gRPC messages, only zstd (2) is supported.
+    pub fn set_compression(&mut self, compression: i32) -> error::Result<()> {
+        self.compression = match compression {
+            0 => false,
+            2 => true,
+            _ => return error::InvalidCompressionSnafu { compression }.fail(),
+        };
+        Ok(())
+    }
+
+    /// Sets the number of worker threads of the client runtime, 0 means the
+    /// number of CPUs.
+    pub fn set_runtime_threads(&mut self, threads: usize) {
+        self.runtime_threads = threads;
+    }
+
+    /// Sets the retry policy of transient failures, requests are sent once
+    /// by default.
+    pub fn set_retry(&mut self, retry: RetryPolicy) {
+        self.retry = retry;
+    }
+
+    pub(crate) fn timeout(&self) -> Duration {
+        self.timeout
+            .unwrap_or(Duration::from_secs(DEFAULT_GRPC_REQUEST_TIMEOUT_SECS))
+    }
+
+    pub(crate) fn tls(&self) -> Option<&TlsConfig> {
+        self.tls.as_ref()
+    }
+
+    pub(crate) fn validate(&self) -> error::Result<()> {
+        ensure!(!self.endpoints.is_empty(), error::EmptyEndpointsSnafu);
+        Ok(())
+    }
+
+    pub(crate) fn channel_config(&self) -> ChannelConfig {
+        let mut config = ChannelConfig::default();
+        if let Some(timeout) = self.connect_timeout {
+            config = config.connect_timeout(timeout);
+        }
+        // Requests are bounded by the client instead, so that they can be given
+        // longer timeouts and long-lived streams are not cut off.
+        config.timeout = None;
+        if let Some(tls) = self.tls.as_ref().and_then(TlsConfig::to_client_tls_option) {
+            config = config.client_tls_config(tls);
+        }
+        config.send_compression = self.compression;
+        config.accept_compression = self.compression;
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ErrorExt, StatusCode};
+    use std::ptr;
+
+    #[test]
+    fn tls_options_from_c() {
+        let mut options = TlsOptions {
+            ca_cert_path: c"ca.pem".as_ptr(),
+            client_cert_path: ptr::null(),
+            client_key_path: ptr::null(),
+            server_name: c"greptime.test".as_ptr(),
+        };
+        let tls = options.to_tls_config().unwrap();
+        assert_eq!(tls.ca_cert_path, "ca.pem");
+        assert!(tls.client_identity.is_none());
+        assert_eq!(tls.server_name.as_deref(), Some("greptime.test"));
+        // The ingester requires mutual TLS without server name override.
+        assert!(tls.to_client_tls_option().is_none());
+
+        options.client_cert_path = c"client.pem".as_ptr();
+        let err = options.to_tls_config().unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::InvalidArgument);
+
+        options.client_key_path = c"client-key.pem".as_ptr();
+        options.server_name = ptr::null();
+        let tls = options.to_tls_config().unwrap();
+        assert_eq!(
+            tls.client_identity,
+            Some(("client.pem".to_string(), "client-key.pem".to_string()))
+        );
+        assert!(tls.to_client_tls_option().is_some());
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::future::Future;
+use std::time::Duration;
+
+use crate::error::{StatusCode, ingester_status_code};
+use tracing::warn;
+
+/// Retry policy of requests, the C layout is `RetryOptions`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_backoff_ms: u64,
+    max_backoff_ms: u64,
+    jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    /// Requests are not retried by default.
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            base_backoff_ms: 100,
+            max_backoff_ms: 10_000,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    #[cfg(test)]
+    pub fn new(max_attempts: u32, base_backoff_ms: u64, max_backoff_ms: u64, jitter: bool) -> Self {
+        Self {
+            max_attempts,
+            base_backoff_ms,
+            max_backoff_ms,
+            jitter,
+        }
+    }
+
+    /// Returns the delay before the attempt after `attempt`, which doubles
+    /// from the base backoff up to the max backoff. With jitter, the delay is
+    /// randomly chosen from its upper half.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .base_backoff_ms
+            .saturating_mul(1 << (attempt - 1).min(32))
+            .min(self.max_backoff_ms);
+        let backoff = if self.jitter && backoff > 1 {
+            backoff / 2 + rand::random_range(0..=backoff - backoff / 2)
+        } else {
+            backoff
+        };
+        Duration::from_millis(backoff)
+    }
+
+    /// Sends `request` by `f` until it succeeds, fails with a non-retryable
+    /// error or the max attempts are used. `attempts` is set to the number of
+    /// attempts made so far.
+    pub async fn retry<R, T, F, Fut>(
+        &self,
+        request: R,
+        attempts: &mut u32,
+        mut f: F,
+    ) -> greptimedb_ingester::Result<T>
+    where
+        R: Clone,
+        F: FnMut(R) -> Fut,
+        Fut: Future<Output = greptimedb_ingester::Result<T>>,
+    {
+        let max_attempts = self.max_attempts.max(1);
+        let mut request = Some(request);
+        *attempts = 0;
+        loop {
+            *attempts += 1;
+            // Only clone the request if it may be retried.
+            let req = if *attempts == max_attempts {
+                request.take()
+            } else {
+                request.clone()
+            };
+            // safety: request is only taken by the last attempt.
+            match f(req.unwrap()).await {
+                Err(e) if *attempts < max_attempts && is_retryable(&e) => {
+                    let backoff = self.backoff(*attempts);
+                    warn!(attempt = *attempts, err = %e, "Retrying request after {:?}", backoff);
+                    tokio::time::sleep(backoff).await;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+/// Returns true if the error is transient, so the request may succeed later,
+/// including server codes like RegionBusy sent with other gRPC codes.
+fn is_retryable(e: &greptimedb_ingester::Error) -> bool {
+    matches!(
+        ingester_status_code(e),
+        StatusCode::ServerUnavailable | StatusCode::Timeout
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let policy = RetryPolicy::new(10, 100, 1000, false);
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(4), Duration::from_millis(800));
+        assert_eq!(policy.backoff(5), Duration::from_millis(1000));
+        assert_eq!(policy.backoff(64), Duration::from_millis(1000));
+
+        let policy = RetryPolicy::new(10, 100, 1000, true);
+        for _ in 0..100 {
+            let backoff = policy.backoff(2);
+            assert!(backoff >= Duration::from_millis(100) && backoff <= Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn retries_transient_errors_only() {
+        let error = |code, server_code: Option<u32>| {
+            let mut status = tonic::Status::new(code, "error");
+            if let Some(server_code) = server_code {
+                status
+                    .metadata_mut()
+                    .insert("x-greptime-err-code", server_code.into());
+            }
+            greptimedb_ingester::Error::from(status)
+        };
+        let cases = [
+            (tonic::Code::Unavailable, None, true),
+            (tonic::Code::DeadlineExceeded, None, true),
+            (tonic::Code::ResourceExhausted, None, true),
+            (tonic::Code::Aborted, None, false),
+            (tonic::Code::Internal, None, false),
+            (tonic::Code::InvalidArgument, None, false),
+            // RegionNotReady, RegionBusy, TableUnavailable and RateLimited.
+            (tonic::Code::Internal, Some(4008), true),
+            (tonic::Code::Internal, Some(4009), true),
+            (tonic::Code::Internal, Some(4010), true),
+            (tonic::Code::Internal, Some(6001), true),
+            // TableColumnNotFound is not transient whatever the gRPC code is.
+            (tonic::Code::Unavailable, Some(4002), false),
+        ];
+        for (code, server_code, expected) in cases {
+            let err = error(code, server_code);
+            assert_eq!(is_retryable(&err), expected, "{code:?} {server_code:?}");
+        }
+    }
+}
@@ -14,15 +14,34 @@
 
 use std::mem::ManuallyDrop;
 
+use crate::column::{ColumnData, check_columns};
 use crate::error;
 use crate::util::{convert_c_binary, convert_c_string};
 use greptimedb_ingester::SemanticType;
+use greptimedb_ingester::api::v1::column_data_type_extension::TypeExt;
 use greptimedb_ingester::api::v1::{
-    ColumnDataType, ColumnSchema, Row, RowInsertRequest, Rows, Value as RowValue, value::ValueData,
+    ColumnDataType, ColumnDataTypeExtension, ColumnOptions, ColumnSchema, Decimal128,
+    DecimalTypeExtension, IntervalMonthDayNano, JsonTypeExtension, Row, RowDeleteRequest,
+    RowInsertRequest, Rows, Value as RowValue, VectorTypeExtension, value::ValueData,
 };
 use snafu::{ResultExt, ensure};
 use tracing::debug;
 
+/// Max precision of a Decimal128 column, same as GreptimeDB.
+const DECIMAL128_MAX_PRECISION: i32 = 38;
+
+/// Flags of [ColumnIndexOptions::indexes].
+const INVERTED_INDEX: u32 = 1;
+const FULLTEXT_INDEX: u32 = 1 << 1;
+const SKIPPING_INDEX: u32 = 1 << 2;
+
+/// Column option keys understood by GreptimeDB when creating tables.
+const INVERTED_INDEX_KEY: &str = "inverted_index";
+const FULLTEXT_KEY: &str = "fulltext";
+const SKIPPING_INDEX_KEY: &str = "skipping_index";
+
+const DEFAULT_SKIPPING_GRANULARITY: u32 = 10240;
+
 #[repr(C)]
 pub union Value {
     pub bool_value: libc::c_char,
@@ -40,21 +59,112 @@ pub union Value {
     pub timestamp_millisecond_value: libc::c_long,
     pub timestamp_microsecond_value: libc::c_long,
     pub timestamp_nanosecond_value: libc::c_long,
+    pub date_value: libc::c_int,
+    pub datetime_value: libc::c_long,
+    pub time_second_value: libc::c_long,
+    pub time_millisecond_value: libc::c_long,
+    pub time_microsecond_value: libc::c_long,
+    pub time_nanosecond_value: libc::c_long,
+    pub interval_year_month_value: libc::c_int,
+    pub interval_day_time_value: libc::c_long,
+    pub interval_month_day_nano_value: IntervalMonthDayNanoValue,
     pub binary_value: ManuallyDrop<BinaryValue>,
     pub string_value: *const libc::c_char,
+    pub decimal128_value: Decimal128Value,
+    pub json_value: *const libc::c_char,
+    pub vector_value: VectorValue,
 }
 
 #[repr(C)]
 pub struct BinaryValue {
-    data: *mut u8,
+    pub(crate) data: *mut u8,
+    pub(crate) len: usize,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct IntervalMonthDayNanoValue {
+    months: i32,
+    days: i32,
+    nanoseconds: i64,
+}
+
+/// Unscaled value of a decimal, split into high and low 64 bits.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Decimal128Value {
+    pub(crate) hi: i64,
+    pub(crate) lo: i64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct VectorValue {
+    data: *const f32,
     len: usize,
 }
 
+/// Index options of a column, only take effect when the table is auto-created.
+#[repr(C)]
+pub struct ColumnIndexOptions {
+    indexes: u32,
+    fulltext_analyzer: i32,
+    fulltext_case_sensitive: bool,
+    skipping_granularity: u32,
+}
+
+impl ColumnIndexOptions {
+    fn to_column_options(&self, name: &str) -> error::Result<ColumnOptions> {
+        let mut options = ColumnOptions::default();
+        if self.indexes & INVERTED_INDEX != 0 {
+            options
+                .options
+                .insert(INVERTED_INDEX_KEY.to_string(), "true".to_string());
+        }
+        if self.indexes & FULLTEXT_INDEX != 0 {
+            let analyzer = match self.fulltext_analyzer {
+                0 => "English",
+                1 => "Chinese",
+                _ => {
+                    return error::InvalidIndexOptionsSnafu {
+                        name,
+                        reason: format!("unknown fulltext analyzer {}", self.fulltext_analyzer),
+                    }
+                    .fail();
+                }
+            };
+            let fulltext = serde_json::json!({
+                "enable": true,
+                "analyzer": analyzer,
+                "case-sensitive": self.fulltext_case_sensitive,
+            });
+            options
+                .options
+                .insert(FULLTEXT_KEY.to_string(), fulltext.to_string());
+        }
+        if self.indexes & SKIPPING_INDEX != 0 {
+            let granularity = match self.skipping_granularity {
+                0 => DEFAULT_SKIPPING_GRANULARITY,
+                g => g,
+            };
+            let skipping = serde_json::json!({
+                "granularity": granularity,
+                "index-type": "BloomFilter",
+            });
+            options
+                .options
+                .insert(SKIPPING_INDEX_KEY.to_string(), skipping.to_string());
+        }
+        Ok(options)
+    }
+}
+
 #[repr(C)]
 pub struct RowBuilder {
     table_name: String,
     schema: Vec<ColumnSchema>,
     rows: Vec<Row>,
+    hints: Vec<(String, String)>,
 }
 
 impl RowBuilder {
@@ -63,7 +173,68 @@ impl RowBuilder {
             table_name,
             schema: vec![],
             rows: vec![],
+            hints: vec![],
+        }
+    }
+
+    /// Sets a hint sent along with inserts of this builder, e.g. `ttl` or
+    /// `append_mode`. Setting an existing key replaces its value. Keys and values
+    /// are printable ASCII without spaces, ',' and '='.
+    pub fn set_hint(&mut self, key: String, value: String) -> error::Result<()> {
+        let is_valid = |s: &str| {
+            !s.is_empty()
+                && s.chars()
+                    .all(|c| c.is_ascii_graphic() && c != ',' && c != '=')
+        };
+        ensure!(
+            is_valid(&key) && is_valid(&value),
+            error::InvalidHintSnafu {
+                key: &key,
+                value: &value,
+            }
+        );
+        debug!("Setting hint of {}: {}={}", &self.table_name, key, value);
+
+        match self.hints.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => *v = value,
+            None => self.hints.push((key, value)),
         }
+        Ok(())
+    }
+
+    pub fn table_name(&self) -> &str {
+        &self.table_name
+    }
+
+    pub fn schema(&self) -> &[ColumnSchema] {
+        &self.schema
+    }
+
+    /// Checks all columns are tags or the time index, which identify rows to delete.
+    pub fn check_delete_columns(&self) -> error::Result<()> {
+        for col in &self.schema {
+            ensure!(
+                col.semantic_type != SemanticType::Field as i32,
+                error::InvalidDeleteColumnSnafu {
+                    table_name: &self.table_name,
+                    column: &col.column_name,
+                }
+            );
+        }
+        Ok(())
+    }
+
+    pub fn hints(&self) -> Vec<(&str, &str)> {
+        self.hints
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect()
+    }
+
+    /// Returns true if the builder has exactly `hints`, whatever order they
+    /// were set in.
+    pub fn has_same_hints(&self, hints: &[(String, String)]) -> bool {
+        self.hints.len() == hints.len() && self.hints.iter().all(|hint| hints.contains(hint))
     }
 
     pub fn add_col(
@@ -71,6 +242,88 @@ impl RowBuilder {
         name: String,
         data_type: i32,
         semantic_type: i32,
+    ) -> error::Result<()> {
+        self.add_col_with_extension(name, data_type, semantic_type, None)
+    }
+
+    pub fn add_decimal128_col(
+        &mut self,
+        name: String,
+        semantic_type: i32,
+        precision: i32,
+        scale: i32,
+    ) -> error::Result<()> {
+        ensure!(
+            (1..=DECIMAL128_MAX_PRECISION).contains(&precision) && (0..=precision).contains(&scale),
+            error::InvalidDecimalTypeSnafu {
+                name: &name,
+                precision,
+                scale,
+            }
+        );
+        let extension = ColumnDataTypeExtension {
+            type_ext: Some(TypeExt::DecimalType(DecimalTypeExtension {
+                precision,
+                scale,
+            })),
+        };
+        self.add_col_with_extension(
+            name,
+            ColumnDataType::Decimal128 as i32,
+            semantic_type,
+            Some(extension),
+        )
+    }
+
+    pub fn add_vector_col(
+        &mut self,
+        name: String,
+        semantic_type: i32,
+        dim: u32,
+    ) -> error::Result<()> {
+        ensure!(dim > 0, error::InvalidVectorTypeSnafu { name: &name, dim });
+        let extension = ColumnDataTypeExtension {
+            type_ext: Some(TypeExt::VectorType(VectorTypeExtension { dim })),
+        };
+        self.add_col_with_extension(
+            name,
+            ColumnDataType::Vector as i32,
+            semantic_type,
+            Some(extension),
+        )
+    }
+
+    pub fn set_col_index_options(
+        &mut self,
+        name: &str,
+        index_options: &ColumnIndexOptions,
+    ) -> error::Result<()> {
+        let Some(col) = self.schema.iter_mut().find(|c| c.column_name == name) else {
+            return error::ColumnNotFoundSnafu { name }.fail();
+        };
+        ensure!(
+            index_options.indexes & FULLTEXT_INDEX == 0
+                || col.datatype == ColumnDataType::String as i32,
+            error::InvalidIndexOptionsSnafu {
+                name,
+                reason: "fulltext index requires a string column",
+            }
+        );
+        let options = index_options.to_column_options(name)?;
+        debug!(
+            "Setting column options of {}.{}: {:?}",
+            &self.table_name, name, options.options
+        );
+        col.options = (!options.options.is_empty()).then_some(options);
+        Ok(())
+    }
+
+    fn add_col_with_extension(
+        &mut self,
+        name: String,
+        data_type: i32,
+        semantic_type: i32,
+        datatype_extension: Option<ColumnDataTypeExtension>,
     ) -> error::Result<()> {
         let data_type =
             ColumnDataType::try_from(data_type).context(error::InvalidColumnDefSnafu {
@@ -89,16 +342,47 @@ impl RowBuilder {
             &self.table_name, name, data_type, semantic_type
         );
 
+        // JSON columns are sent as strings and need the extension to be
+        // recognized as JSON by the server.
+        let datatype_extension = match data_type {
+            ColumnDataType::Json => datatype_extension.or(Some(ColumnDataTypeExtension {
+                type_ext: Some(TypeExt::JsonType(JsonTypeExtension::JsonBinary as i32)),
+            })),
+            _ => datatype_extension,
+        };
+
         self.schema.push(ColumnSchema {
             column_name: name,
             datatype: data_type as i32,
             semantic_type: semantic_type as i32,
+            datatype_extension,
             ..Default::default()
         });
         Ok(())
     }
 
     pub unsafe fn add_row(&mut self, values: &[Value]) -> error::Result<()> {
+        unsafe { self.push_row(values, None) }
+    }
+
+    /// Adds a row where `nulls[i]` marks the i-th value as NULL. Values of
+    /// null columns are never read.
+    pub unsafe fn add_nullable_row(
+        &mut self,
+        values: &[Value],
+        nulls: &[bool],
+    ) -> error::Result<()> {
+        ensure!(
+            self.schema.len() == nulls.len(),
+            error::SchemaMismatchSnafu {
+                value_len: nulls.len(),
+                schema_len: self.schema.len(),
+            }
+        );
+        unsafe { self.push_row(values, Some(nulls)) }
+    }
+
+    unsafe fn push_row(&mut self, values: &[Value], nulls: Option<&[bool]>) -> error::Result<()> {
         debug!("Adding values, len: {}", values.len());
         ensure!(
             self.schema.len() == values.len(),
@@ -108,63 +392,221 @@ impl RowBuilder {
             }
         );
         let mut row_values = Vec::with_capacity(values.len());
-        for (col, val) in self.schema.iter().zip(values.iter()) {
-            // safety: we've checked the validity of data type value in [add_column].
-            let data_type = ColumnDataType::try_from(col.datatype).unwrap();
-
-            let value_data = match data_type {
-                ColumnDataType::Boolean => {
-                    Some(ValueData::BoolValue(unsafe { val.bool_value } == 1))
-                }
-                ColumnDataType::Int8 => Some(ValueData::I8Value(unsafe { val.i8_value } as i32)),
-                ColumnDataType::Int16 => Some(ValueData::I16Value(unsafe { val.i16_value } as i32)),
-                ColumnDataType::Int32 => Some(ValueData::I32Value(unsafe { val.i32_value })),
-                ColumnDataType::Int64 => Some(ValueData::I64Value(unsafe { val.i64_value })),
-                ColumnDataType::Uint8 => Some(ValueData::U8Value(unsafe { val.u8_value } as u32)),
-                ColumnDataType::Uint16 => {
-                    Some(ValueData::U16Value(unsafe { val.u16_value } as u32))
-                }
-                ColumnDataType::Uint32 => Some(ValueData::U32Value(unsafe { val.u32_value })),
-                ColumnDataType::Uint64 => Some(ValueData::U64Value(unsafe { val.u64_value })),
-                ColumnDataType::Float32 => Some(ValueData::F32Value(unsafe { val.f32_value })),
-                ColumnDataType::Float64 => Some(ValueData::F64Value(unsafe { val.f64_value })),
-                ColumnDataType::Binary => Some(ValueData::BinaryValue(convert_c_binary(
-                    unsafe { val.binary_value.data },
-                    unsafe { val.binary_value.len },
-                )?)),
-                ColumnDataType::String => Some(ValueData::StringValue(convert_c_string(unsafe {
-                    val.string_value
-                })?)),
-                ColumnDataType::TimestampSecond => Some(ValueData::TimestampSecondValue(unsafe {
-                    val.timestamp_second_value
-                })),
-                ColumnDataType::TimestampMillisecond => {
-                    Some(ValueData::TimestampMillisecondValue(unsafe {
-                        val.timestamp_millisecond_value
-                    }))
-                }
-                ColumnDataType::TimestampMicrosecond => {
-                    Some(ValueData::TimestampMicrosecondValue(unsafe {
-                        val.timestamp_microsecond_value
-                    }))
-                }
-                ColumnDataType::TimestampNanosecond => {
-                    Some(ValueData::TimestampNanosecondValue(unsafe {
-                        val.timestamp_nanosecond_value
-                    }))
-                }
-                _ => {
-                    return error::UnsupportedDataTypeSnafu {
-                        data_type: col.datatype,
+        for (idx, (col, val)) in self.schema.iter().zip(values.iter()).enumerate() {
+            if nulls.is_some_and(|nulls| nulls[idx]) {
+                ensure!(
+                    col.semantic_type == SemanticType::Field as i32,
+                    error::NullValueSnafu {
+                        column: &col.column_name,
                     }
-                    .fail();
-                }
-            };
-            row_values.push(RowValue { value_data });
+                );
+                row_values.push(RowValue { value_data: None });
+                continue;
+            }
+
+            let value_data = unsafe { to_value_data(col, val) }?;
+            row_values.push(RowValue {
+                value_data: Some(value_data),
+            });
         }
         self.rows.push(Row { values: row_values });
         Ok(())
     }
+
+    /// Adds `rows` rows given one array of values per column, see [ColumnData].
+    /// No row is added if any value is invalid.
+    pub unsafe fn add_columns(&mut self, columns: &[ColumnData], rows: usize) -> error::Result<()> {
+        debug!("Adding columns, len: {}, rows: {}", columns.len(), rows);
+        check_columns(&self.schema, columns, rows)?;
+
+        let mut new_rows = Vec::with_capacity(rows);
+        for row in 0..rows {
+            let mut row_values = Vec::with_capacity(columns.len());
+            for (col, data) in self.schema.iter().zip(columns) {
+                let value_data = if data.is_null(row) {
+                    None
+                } else {
+                    let val = unsafe { data.value(col, row) }?;
+                    Some(unsafe { to_value_data(col, &val) }?)
+                };
+                row_values.push(RowValue { value_data });
+            }
+            new_rows.push(Row { values: row_values });
+        }
+        self.rows.append(&mut new_rows);
+        Ok(())
+    }
+}
+
+/// Converts a non-null value of the column to protobuf value.
+unsafe fn to_value_data(col: &ColumnSchema, val: &Value) -> error::Result<ValueData> {
+    // safety: we've checked the validity of data type value in [add_column].
+    let data_type = ColumnDataType::try_from(col.datatype).unwrap();
+
+    let value_data = match data_type {
+        ColumnDataType::Boolean => ValueData::BoolValue(unsafe { val.bool_value } == 1),
+        ColumnDataType::Int8 => ValueData::I8Value(unsafe { val.i8_value } as i32),
+        ColumnDataType::Int16 => ValueData::I16Value(unsafe { val.i16_value } as i32),
+        ColumnDataType::Int32 => ValueData::I32Value(unsafe { val.i32_value }),
+        ColumnDataType::Int64 => ValueData::I64Value(unsafe { val.i64_value }),
+        ColumnDataType::Uint8 => ValueData::U8Value(unsafe { val.u8_value } as u32),
+        ColumnDataType::Uint16 => ValueData::U16Value(unsafe { val.u16_value } as u32),
+        ColumnDataType::Uint32 => ValueData::U32Value(unsafe { val.u32_value }),
+        ColumnDataType::Uint64 => ValueData::U64Value(unsafe { val.u64_value }),
+        ColumnDataType::Float32 => ValueData::F32Value(unsafe { val.f32_value }),
+        ColumnDataType::Float64 => ValueData::F64Value(unsafe { val.f64_value }),
+        ColumnDataType::Binary => ValueData::BinaryValue(convert_c_binary(
+            unsafe { val.binary_value.data },
+            unsafe { val.binary_value.len },
+        )?),
+        ColumnDataType::String => {
+            ValueData::StringValue(convert_c_string(unsafe { val.string_value })?)
+        }
+        ColumnDataType::TimestampSecond => {
+            ValueData::TimestampSecondValue(unsafe { val.timestamp_second_value })
+        }
+        ColumnDataType::TimestampMillisecond => {
+            ValueData::TimestampMillisecondValue(unsafe { val.timestamp_millisecond_value })
+        }
+        ColumnDataType::TimestampMicrosecond => {
+            ValueData::TimestampMicrosecondValue(unsafe { val.timestamp_microsecond_value })
+        }
+        ColumnDataType::TimestampNanosecond => {
+            ValueData::TimestampNanosecondValue(unsafe { val.timestamp_nanosecond_value })
+        }
+        ColumnDataType::Date => ValueData::DateValue(unsafe { val.date_value }),
+        ColumnDataType::Datetime => ValueData::DatetimeValue(unsafe { val.datetime_value }),
+        ColumnDataType::TimeSecond => ValueData::TimeSecondValue(unsafe { val.time_second_value }),
+        ColumnDataType::TimeMillisecond => {
+            ValueData::TimeMillisecondValue(unsafe { val.time_millisecond_value })
+        }
+        ColumnDataType::TimeMicrosecond => {
+            ValueData::TimeMicrosecondValue(unsafe { val.time_microsecond_value })
+        }
+        ColumnDataType::TimeNanosecond => {
+            ValueData::TimeNanosecondValue(unsafe { val.time_nanosecond_value })
+        }
+        ColumnDataType::IntervalYearMonth => {
+            ValueData::IntervalYearMonthValue(unsafe { val.interval_year_month_value })
+        }
+        ColumnDataType::IntervalDayTime => {
+            ValueData::IntervalDayTimeValue(unsafe { val.interval_day_time_value })
+        }
+        ColumnDataType::IntervalMonthDayNano => {
+            let IntervalMonthDayNanoValue {
+                months,
+                days,
+                nanoseconds,
+            } = unsafe { val.interval_month_day_nano_value };
+            ValueData::IntervalMonthDayNanoValue(IntervalMonthDayNano {
+                months,
+                days,
+                nanoseconds,
+            })
+        }
+        ColumnDataType::Decimal128 => {
+            let Decimal128Value { hi, lo } = unsafe { val.decimal128_value };
+            check_decimal128_precision(col, hi, lo)?;
+            ValueData::Decimal128Value(Decimal128 { hi, lo })
+        }
+        ColumnDataType::Json => {
+            let json = convert_c_string(unsafe { val.json_value })?;
+            serde_json::from_str::<serde::de::IgnoredAny>(&json).context(
+                error::InvalidJsonSnafu {
+                    column: &col.column_name,
+                },
+            )?;
+            ValueData::StringValue(json)
+        }
+        ColumnDataType::Vector => {
+            let VectorValue { data, len } = unsafe { val.vector_value };
+            check_vector_dim(col, len)?;
+            ValueData::BinaryValue(encode_vector(data, len)?)
+        }
+        _ => {
+            return error::UnsupportedDataTypeSnafu {
+                data_type: col.datatype,
+            }
+            .fail();
+        }
+    };
+    Ok(value_data)
+}
+
+/// Returns the hints of builders written in one request, which apply to all
+/// tables of the request, so builders with different hints are rejected.
+pub fn same_hints(builders: &[&mut RowBuilder]) -> error::Result<Vec<(String, String)>> {
+    let Some((first, others)) = builders.split_first() else {
+        return Ok(vec![]);
+    };
+    for other in others {
+        ensure!(
+            other.has_same_hints(&first.hints),
+            error::MismatchedHintsSnafu {
+                table_name: &first.table_name,
+                other: &other.table_name,
+            }
+        );
+    }
+    Ok(first.hints.clone())
+}
+
+/// Ensures the unscaled decimal fits into the precision declared by the column,
+/// columns without a declared precision use the max precision.
+pub(crate) fn check_decimal128_precision(
+    col: &ColumnSchema,
+    hi: i64,
+    lo: i64,
+) -> error::Result<()> {
+    let precision = match &col.datatype_extension {
+        Some(ColumnDataTypeExtension {
+            type_ext: Some(TypeExt::DecimalType(ext)),
+        }) => ext.precision,
+        _ => DECIMAL128_MAX_PRECISION,
+    };
+    let value = ((hi as i128) << 64) | (lo as u64 as i128);
+    ensure!(
+        value.unsigned_abs() < 10u128.pow(precision as u32),
+        error::DecimalOverflowSnafu {
+            column: &col.column_name,
+            value,
+            precision,
+        }
+    );
+    Ok(())
+}
+
+/// Ensures the vector length matches the dimension declared by the column.
+fn check_vector_dim(col: &ColumnSchema, len: usize) -> error::Result<()> {
+    let dim = match &col.datatype_extension {
+        Some(ColumnDataTypeExtension {
+            type_ext: Some(TypeExt::VectorType(ext)),
+        }) => ext.dim,
+        _ => {
+            return error::InvalidVectorTypeSnafu {
+                name: &col.column_name,
+                dim: 0u32,
+            }
+            .fail();
+        }
+    };
+    ensure!(
+        dim as usize == len,
+        error::VectorDimMismatchSnafu {
+            column: &col.column_name,
+            dim,
+            len,
+        }
+    );
+    Ok(())
+}
+
+/// Encodes vector as little-endian f32 bytes, which is how GreptimeDB stores vectors.
+fn encode_vector(data: *const f32, len: usize) -> error::Result<Vec<u8>> {
+    ensure!(!data.is_null(), error::NullPointerSnafu);
+
+    let slice = unsafe { std::slice::from_raw_parts(data, len) };
+    Ok(slice.iter().flat_map(|v| v.to_le_bytes()).collect())
 }
 
 impl From<&mut RowBuilder> for RowInsertRequest {
@@ -179,9 +621,22 @@ impl From<&mut RowBuilder> for RowInsertRequest {
     }
 }
 
+impl From<&mut RowBuilder> for RowDeleteRequest {
+    fn from(value: &mut RowBuilder) -> Self {
+        RowDeleteRequest {
+            table_name: value.table_name.clone(),
+            rows: Some(Rows {
+                schema: value.schema.clone(),
+                rows: std::mem::take(&mut value.rows),
+            }),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::{ErrorExt, StatusCode};
 
     #[test]
     fn row_builder_builds_row_based_request() {
@@ -252,4 +707,485 @@ mod tests {
             Some(ValueData::I32Value(2))
         ));
     }
+
+    #[test]
+    fn row_builder_accepts_null_fields_only() {
+        let mut builder = RowBuilder::new("demo".to_string());
+        builder
+            .add_col(
+                "ts".to_string(),
+                ColumnDataType::TimestampMillisecond as i32,
+                SemanticType::Timestamp as i32,
+            )
+            .unwrap();
+        builder
+            .add_col(
+                "value".to_string(),
+                ColumnDataType::Float64 as i32,
+                SemanticType::Field as i32,
+            )
+            .unwrap();
+
+        let values = [
+            Value {
+                timestamp_millisecond_value: 1,
+            },
+            Value { f64_value: 0.0 },
+        ];
+        unsafe {
+            builder.add_nullable_row(&values, &[false, true]).unwrap();
+            let err = builder
+                .add_nullable_row(&values, &[true, false])
+                .unwrap_err();
+            assert!(matches!(err, error::Error::NullValue { .. }));
+            assert!(builder.add_nullable_row(&values, &[false]).is_err());
+        }
+
+        let req: RowInsertRequest = (&mut builder).into();
+        let rows = req.rows.unwrap();
+        assert_eq!(rows.rows.len(), 1);
+        assert!(matches!(
+            rows.rows[0].values[0].value_data,
+            Some(ValueData::TimestampMillisecondValue(1))
+        ));
+        assert!(rows.rows[0].values[1].value_data.is_none());
+    }
+
+    #[test]
+    fn row_builder_adds_columns() {
+        let mut builder = RowBuilder::new("demo".to_string());
+        builder
+            .add_col(
+                "host".to_string(),
+                ColumnDataType::String as i32,
+                SemanticType::Tag as i32,
+            )
+            .unwrap();
+        builder
+            .add_col(
+                "ts".to_string(),
+                ColumnDataType::TimestampMillisecond as i32,
+                SemanticType::Timestamp as i32,
+            )
+            .unwrap();
+        builder
+            .add_vector_col("v".to_string(), SemanticType::Field as i32, 2)
+            .unwrap();
+
+        let hosts = [c"a".as_ptr(), c"b".as_ptr()];
+        let ts = [1i64, 2];
+        let v = [1.0f32, 2.0];
+        let vectors = [
+            VectorValue {
+                data: v.as_ptr(),
+                len: 2,
+            },
+            VectorValue {
+                data: std::ptr::null(),
+                len: 0,
+            },
+        ];
+        let nulls = [0b10u8];
+        let columns = [
+            ColumnData {
+                values: hosts.as_ptr() as _,
+                nulls: std::ptr::null(),
+            },
+            ColumnData {
+                values: ts.as_ptr() as _,
+                nulls: std::ptr::null(),
+            },
+            ColumnData {
+                values: vectors.as_ptr() as _,
+                nulls: nulls.as_ptr(),
+            },
+        ];
+        let invalid = [
+            ColumnData {
+                values: hosts.as_ptr() as _,
+                nulls: std::ptr::null(),
+            },
+            ColumnData {
+                values: ts.as_ptr() as _,
+                nulls: std::ptr::null(),
+            },
+            // the second vector has wrong dimension
+            ColumnData {
+                values: vectors.as_ptr() as _,
+                nulls: std::ptr::null(),
+            },
+        ];
+        unsafe {
+            builder.add_columns(&columns, 2).unwrap();
+            let err = builder.add_columns(&invalid, 2).unwrap_err();
+            assert!(matches!(err, error::Error::VectorDimMismatch { .. }));
+            assert!(builder.add_columns(&columns[..2], 2).is_err());
+        }
+
+        let req: RowInsertRequest = (&mut builder).into();
+        let rows = req.rows.unwrap();
+        assert_eq!(rows.rows.len(), 2);
+        assert!(matches!(
+            &rows.rows[1].values[0].value_data,
+            Some(ValueData::StringValue(s)) if s == "b"
+        ));
+        assert!(matches!(
+            rows.rows[1].values[1].value_data,
+            Some(ValueData::TimestampMillisecondValue(2))
+        ));
+        assert!(rows.rows[0].values[2].value_data.is_some());
+        assert!(rows.rows[1].values[2].value_data.is_none());
+    }
+
+    #[test]
+    fn row_builder_converts_date_and_time_values() {
+        let mut builder = RowBuilder::new("demo".to_string());
+        for (name, data_type) in [
+            ("d", ColumnDataType::Date),
+            ("dt", ColumnDataType::Datetime),
+            ("t_s", ColumnDataType::TimeSecond),
+            ("t_ns", ColumnDataType::TimeNanosecond),
+        ] {
+            builder
+                .add_col(
+                    name.to_string(),
+                    data_type as i32,
+                    SemanticType::Field as i32,
+                )
+                .unwrap();
+        }
+
+        unsafe {
+            builder
+                .add_row(&[
+                    Value { date_value: 19675 },
+                    Value {
+                        datetime_value: 1700047510000,
+                    },
+                    Value {
+                        time_second_value: 3600,
+                    },
+                    Value {
+                        time_nanosecond_value: 42,
+                    },
+                ])
+                .unwrap();
+        }
+
+        let req: RowInsertRequest = (&mut builder).into();
+        let values = &req.rows.unwrap().rows[0].values;
+        assert!(matches!(
+            values[0].value_data,
+            Some(ValueData::DateValue(19675))
+        ));
+        assert!(matches!(
+            values[1].value_data,
+            Some(ValueData::DatetimeValue(1700047510000))
+        ));
+        assert!(matches!(
+            values[2].value_data,
+            Some(ValueData::TimeSecondValue(3600))
+        ));
+        assert!(matches!(
+            values[3].value_data,
+            Some(ValueData::TimeNanosecondValue(42))
+        ));
+    }
+
+    #[test]
+    fn row_builder_checks_decimal128_precision() {
+        let mut builder = RowBuilder::new("demo".to_string());
+        assert!(
+            builder
+                .add_decimal128_col("bad".to_string(), SemanticType::Field as i32, 39, 2)
+                .is_err()
+        );
+        assert!(
+            builder
+                .add_decimal128_col("bad".to_string(), SemanticType::Field as i32, 5, 6)
+                .is_err()
+        );
+        builder
+            .add_decimal128_col("amount".to_string(), SemanticType::Field as i32, 5, 2)
+            .unwrap();
+        assert_eq!(builder.schema.len(), 1);
+        assert!(matches!(
+            builder.schema[0].datatype_extension,
+            Some(ColumnDataTypeExtension {
+                type_ext: Some(TypeExt::DecimalType(DecimalTypeExtension {
+                    precision: 5,
+                    scale: 2
+                }))
+            })
+        ));
+
+        unsafe {
+            // -999.99
+            builder
+                .add_row(&[Value {
+                    decimal128_value: Decimal128Value { hi: -1, lo: -99999 },
+                }])
+                .unwrap();
+            let err = builder
+                .add_row(&[Value {
+                    decimal128_value: Decimal128Value { hi: 0, lo: 100000 },
+                }])
+                .unwrap_err();
+            assert!(matches!(err, error::Error::DecimalOverflow { .. }));
+        }
+
+        let req: RowInsertRequest = (&mut builder).into();
+        let rows = req.rows.unwrap();
+        assert_eq!(rows.rows.len(), 1);
+        assert!(matches!(
+            rows.rows[0].values[0].value_data,
+            Some(ValueData::Decimal128Value(Decimal128 {
+                hi: -1,
+                lo: -99999
+            }))
+        ));
+    }
+
+    #[test]
+    fn row_builder_validates_json_values() {
+        let mut builder = RowBuilder::new("demo".to_string());
+        builder
+            .add_col(
+                "doc".to_string(),
+                ColumnDataType::Json as i32,
+                SemanticType::Field as i32,
+            )
+            .unwrap();
+        assert!(matches!(
+            builder.schema[0].datatype_extension,
+            Some(ColumnDataTypeExtension {
+                type_ext: Some(TypeExt::JsonType(ext))
+            }) if ext == JsonTypeExtension::JsonBinary as i32
+        ));
+
+        let valid = c"{\"host\": \"a\", \"cpu\": [1, 2.5]}";
+        let invalid = c"{\"host\": ";
+        unsafe {
+            builder
+                .add_row(&[Value {
+                    json_value: valid.as_ptr(),
+                }])
+                .unwrap();
+            let err = builder
+                .add_row(&[Value {
+                    json_value: invalid.as_ptr(),
+                }])
+                .unwrap_err();
+            assert!(matches!(err, error::Error::InvalidJson { .. }));
+        }
+
+        let req: RowInsertRequest = (&mut builder).into();
+        let rows = req.rows.unwrap();
+        assert_eq!(rows.rows.len(), 1);
+        assert!(matches!(
+            &rows.rows[0].values[0].value_data,
+            Some(ValueData::StringValue(s)) if s == valid.to_str().unwrap()
+        ));
+    }
+
+    #[test]
+    fn row_builder_converts_interval_values() {
+        let mut builder = RowBuilder::new("demo".to_string());
+        for (name, data_type) in [
+            ("ym", ColumnDataType::IntervalYearMonth),
+            ("dt", ColumnDataType::IntervalDayTime),
+            ("mdn", ColumnDataType::IntervalMonthDayNano),
+        ] {
+            builder
+                .add_col(
+                    name.to_string(),
+                    data_type as i32,
+                    SemanticType::Field as i32,
+                )
+                .unwrap();
+        }
+
+        unsafe {
+            builder
+                .add_row(&[
+                    Value {
+                        interval_year_month_value: 14,
+                    },
+                    Value {
+                        interval_day_time_value: 1 << 32,
+                    },
+                    Value {
+                        interval_month_day_nano_value: IntervalMonthDayNanoValue {
+                            months: 1,
+                            days: 2,
+                            nanoseconds: 3,
+                        },
+                    },
+                ])
+                .unwrap();
+        }
+
+        let req: RowInsertRequest = (&mut builder).into();
+        let values = &req.rows.unwrap().rows[0].values;
+        assert!(matches!(
+            values[0].value_data,
+            Some(ValueData::IntervalYearMonthValue(14))
+        ));
+        assert!(matches!(
+            values[1].value_data,
+            Some(ValueData::IntervalDayTimeValue(v)) if v == 1 << 32
+        ));
+        assert!(matches!(
+            values[2].value_data,
+            Some(ValueData::IntervalMonthDayNanoValue(IntervalMonthDayNano {
+                months: 1,
+                days: 2,
+                nanoseconds: 3,
+            }))
+        ));
+    }
+
+    #[test]
+    fn row_builder_encodes_vector_values() {
+        let mut builder = RowBuilder::new("demo".to_string());
+        assert!(
+            builder
+                .add_vector_col("bad".to_string(), SemanticType::Field as i32, 0)
+                .is_err()
+        );
+        builder
+            .add_vector_col("embedding".to_string(), SemanticType::Field as i32, 2)
+            .unwrap();
+
+        let embedding = [1.0f32, -0.5];
+        unsafe {
+            builder
+                .add_row(&[Value {
+                    vector_value: VectorValue {
+                        data: embedding.as_ptr(),
+                        len: 2,
+                    },
+                }])
+                .unwrap();
+            let err = builder
+                .add_row(&[Value {
+                    vector_value: VectorValue {
+                        data: embedding.as_ptr(),
+                        len: 1,
+                    },
+                }])
+                .unwrap_err();
+            assert!(matches!(err, error::Error::VectorDimMismatch { .. }));
+        }
+
+        let req: RowInsertRequest = (&mut builder).into();
+        let rows = req.rows.unwrap();
+        assert_eq!(rows.rows.len(), 1);
+        let expected: Vec<u8> = [1.0f32.to_le_bytes(), (-0.5f32).to_le_bytes()].concat();
+        assert!(matches!(
+            &rows.rows[0].values[0].value_data,
+            Some(ValueData::BinaryValue(v)) if *v == expected
+        ));
+    }
+
+    #[test]
+    fn row_builder_sets_column_index_options() {
+        let mut builder = RowBuilder::new("demo".to_string());
+        builder
+            .add_col(
+                "host".to_string(),
+                ColumnDataType::String as i32,
+                SemanticType::Tag as i32,
+            )
+            .unwrap();
+        builder
+            .add_col(
+                "cpu".to_string(),
+                ColumnDataType::Float64 as i32,
+                SemanticType::Field as i32,
+            )
+            .unwrap();
+
+        let options = ColumnIndexOptions {
+            indexes: INVERTED_INDEX | FULLTEXT_INDEX | SKIPPING_INDEX,
+            fulltext_analyzer: 1,
+            fulltext_case_sensitive: true,
+            skipping_granularity: 0,
+        };
+        builder.set_col_index_options("host", &options).unwrap();
+        assert!(builder.set_col_index_options("cpu", &options).is_err());
+        assert!(builder.set_col_index_options("missing", &options).is_err());
+
+        let opts = &builder.schema[0].options.as_ref().unwrap().options;
+        assert_eq!(opts[INVERTED_INDEX_KEY], "true");
+        let fulltext: serde_json::Value = serde_json::from_str(&opts[FULLTEXT_KEY]).unwrap();
+        assert_eq!(fulltext["enable"], true);
+        assert_eq!(fulltext["analyzer"], "Chinese");
+        assert_eq!(fulltext["case-sensitive"], true);
+        let skipping: serde_json::Value = serde_json::from_str(&opts[SKIPPING_INDEX_KEY]).unwrap();
+        assert_eq!(skipping["granularity"], DEFAULT_SKIPPING_GRANULARITY);
+        assert!(builder.schema[1].options.is_none());
+
+        let req: RowInsertRequest = (&mut builder).into();
+        assert!(req.rows.unwrap().schema[0].options.is_some());
+    }
+
+    #[test]
+    fn row_builder_sets_hints() {
+        let mut builder = RowBuilder::new("demo".to_string());
+        builder
+            .set_hint("ttl".to_string(), "7d".to_string())
+            .unwrap();
+        builder
+            .set_hint("append_mode".to_string(), "true".to_string())
+            .unwrap();
+        builder
+            .set_hint("ttl".to_string(), "30d".to_string())
+            .unwrap();
+        assert!(
+            builder
+                .set_hint("ttl".to_string(), "1d,skip_wal=true".to_string())
+                .is_err()
+        );
+        assert!(builder.set_hint(String::new(), "true".to_string()).is_err());
+        // Spaces are not allowed either.
+        assert!(
+            builder
+                .set_hint("ttl".to_string(), "7 days".to_string())
+                .is_err()
+        );
+
+        assert_eq!(
+            builder.hints(),
+            vec![("ttl", "30d"), ("append_mode", "true")]
+        );
+    }
+
+    #[test]
+    fn same_hints_rejects_different_hints() {
+        let mut a = RowBuilder::new("a".to_string());
+        let mut b = RowBuilder::new("b".to_string());
+        a.set_hint("ttl".to_string(), "7d".to_string()).unwrap();
+        a.set_hint("append_mode".to_string(), "true".to_string())
+            .unwrap();
+        b.set_hint("append_mode".to_string(), "true".to_string())
+            .unwrap();
+        b.set_hint("ttl".to_string(), "7d".to_string()).unwrap();
+
+        let hints = same_hints(&[&mut a, &mut b]).unwrap();
+        assert_eq!(
+            hints,
+            vec![
+                ("ttl".to_string(), "7d".to_string()),
+                ("append_mode".to_string(), "true".to_string())
+            ]
+        );
+        assert!(same_hints(&[]).unwrap().is_empty());
+
+        // A hint of one table must not apply to another one.
+        let mut c = RowBuilder::new("c".to_string());
+        let err = same_hints(&[&mut a, &mut c]).unwrap_err();
+        assert!(matches!(err, error::Error::MismatchedHints { .. }));
+        assert_eq!(err.status_code(), StatusCode::InvalidArgument);
+    }
 }
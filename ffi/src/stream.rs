@@ -0,0 +1,179 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::row::RowBuilder;
+use crate::{Client, affected_rows, error};
+use greptimedb_ingester::api::v1::greptime_database_client::GreptimeDatabaseClient;
+use greptimedb_ingester::api::v1::greptime_request::Request;
+use greptimedb_ingester::api::v1::{
+    GreptimeRequest, RequestHeader, RowInsertRequest, RowInsertRequests,
+};
+use snafu::{OptionExt, ResultExt, ensure};
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::Channel;
+use tracing::{debug, info};
+
+/// Max number of requests queued in the stream before writes block.
+const STREAM_BUFFER_SIZE: usize = 128;
+
+/// Sends inserts over a single long-lived gRPC stream, the server acknowledges
+/// all of them once the stream is finished.
+pub struct StreamInserter {
+    runtime: Handle,
+    header: RequestHeader,
+    sender: Option<mpsc::Sender<GreptimeRequest>>,
+    response: Option<JoinHandle<error::Result<u32>>>,
+}
+
+impl StreamInserter {
+    pub fn new(client: &Client) -> Self {
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER_SIZE);
+        let grpc_client = client.endpoints.pick().client.clone();
+        let response = client
+            .runtime
+            .spawn(handle_requests(grpc_client, ReceiverStream::new(rx)));
+
+        Self {
+            runtime: client.runtime.handle().clone(),
+            header: client.endpoints.header.clone(),
+            sender: Some(tx),
+            response: Some(response),
+        }
+    }
+
+    /// Drains rows of the builder into the stream, it blocks only if the
+    /// stream is congested.
+    pub fn write(&mut self, row: &mut RowBuilder) -> error::Result<()> {
+        ensure!(
+            row.hints().is_empty(),
+            error::UnsupportedHintsSnafu {
+                table_name: row.table_name(),
+            }
+        );
+        let sender = self.sender.as_ref().context(error::StreamClosedSnafu)?;
+        let request = GreptimeRequest {
+            header: Some(self.header.clone()),
+            request: Some(Request::RowInserts(RowInsertRequests {
+                inserts: vec![RowInsertRequest::from(row)],
+            })),
+        };
+        if self.runtime.block_on(sender.send(request)).is_err() {
+            // The stream is broken, finish it to surface the cause.
+            self.finish()?;
+            return error::StreamClosedSnafu.fail();
+        }
+        Ok(())
+    }
+
+    /// Closes the stream and returns the number of rows affected by all writes.
+    pub fn finish(&mut self) -> error::Result<u32> {
+        // Dropping the sender ends the request stream.
+        self.sender = None;
+        let response = self.response.take().context(error::StreamClosedSnafu)?;
+        let affected_rows = self
+            .runtime
+            .block_on(response)
+            .ok()
+            .context(error::StreamClosedSnafu)??;
+        debug!("Stream finished, affected rows: {}", affected_rows);
+        Ok(affected_rows)
+    }
+}
+
+impl Drop for StreamInserter {
+    fn drop(&mut self) {
+        info!("Dropping stream inserter");
+    }
+}
+
+async fn handle_requests(
+    mut client: GreptimeDatabaseClient<Channel>,
+    requests: ReceiverStream<GreptimeRequest>,
+) -> error::Result<u32> {
+    debug!("Opening insert stream");
+
+    let response = client
+        .handle_requests(requests)
+        .await
+        .map_err(|e| Box::new(e.into()))
+        .context(error::InsertReqSnafu)?
+        .into_inner();
+    affected_rows(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::row::Value;
+    use crate::test_util;
+    use greptimedb_ingester::SemanticType;
+    use greptimedb_ingester::api::v1::ColumnDataType;
+
+    fn demo_builder(rows: i64) -> RowBuilder {
+        let mut builder = RowBuilder::new("demo".to_string());
+        builder
+            .add_col(
+                "v".to_string(),
+                ColumnDataType::Int64 as i32,
+                SemanticType::Field as i32,
+            )
+            .unwrap();
+        for i in 0..rows {
+            unsafe { builder.add_row(&[Value { i64_value: i }]).unwrap() };
+        }
+        builder
+    }
+
+    #[test]
+    fn stream_inserter_returns_total_affected_rows() {
+        let server = test_util::StubServer::start(Default::default());
+        let client = test_util::new_client(&server.addr());
+
+        let mut stream = StreamInserter::new(&client);
+        for rows in [1, 2, 3] {
+            let mut builder = demo_builder(rows);
+            stream.write(&mut builder).unwrap();
+            // Rows are drained into the stream.
+            assert!(
+                RowInsertRequest::from(&mut builder)
+                    .rows
+                    .unwrap()
+                    .rows
+                    .is_empty()
+            );
+        }
+        assert_eq!(stream.finish().unwrap(), 6);
+        assert!(matches!(
+            stream.finish().unwrap_err(),
+            error::Error::StreamClosed { .. }
+        ));
+    }
+
+    #[test]
+    fn stream_inserter_reports_failure_on_finish() {
+        // Nothing listens on port 1, so the stream fails once it is opened.
+        let client = test_util::new_client("127.0.0.1:1");
+
+        let mut stream = StreamInserter::new(&client);
+        let err = stream.finish().unwrap_err();
+        assert!(matches!(err, error::Error::InsertReq { .. }));
+        assert!(matches!(
+            stream.write(&mut demo_builder(1)).unwrap_err(),
+            error::Error::StreamClosed { .. }
+        ));
+    }
+}
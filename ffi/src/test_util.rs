@@ -0,0 +1,310 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A local GreptimeDatabase server for tests.
+
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::Client;
+use crate::options::ClientOptions;
+use arrow_flight::decode::{DecodedPayload, FlightDataDecoder};
+use arrow_flight::error::FlightError;
+use arrow_flight::flight_service_server::{FlightService, FlightServiceServer};
+use arrow_flight::{
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightInfo,
+    HandshakeRequest, HandshakeResponse, PollInfo, PutResult, SchemaResult, Ticket,
+};
+use futures::{Stream, StreamExt, TryStreamExt};
+use greptimedb_ingester::api::v1::greptime_database_server::{
+    GreptimeDatabase, GreptimeDatabaseServer,
+};
+use greptimedb_ingester::api::v1::greptime_request::Request;
+use greptimedb_ingester::api::v1::{
+    AffectedRows, GreptimeRequest, GreptimeResponse, greptime_response,
+};
+use tokio::net::TcpListener;
+use tokio::runtime::Runtime;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
+use tonic::{Code, Response, Status};
+
+/// A unary request received by [StubDatabase].
+#[derive(Clone, Debug)]
+pub struct Received {
+    /// Value of the `x-greptime-hints` metadata, if any.
+    pub hints: Option<String>,
+    pub request: GreptimeRequest,
+}
+
+/// Answers every request with the number of rows in it.
+struct StubDatabase {
+    delay: Duration,
+    failures: Mutex<VecDeque<Code>>,
+    received: Arc<Mutex<Vec<Received>>>,
+}
+
+#[tonic::async_trait]
+impl GreptimeDatabase for StubDatabase {
+    async fn handle(
+        &self,
+        request: tonic::Request<GreptimeRequest>,
+    ) -> Result<Response<GreptimeResponse>, Status> {
+        let hints = request
+            .metadata()
+            .get(crate::HINTS_KEY)
+            .map(|hints| hints.to_str().unwrap().to_string());
+        let request = request.into_inner();
+        self.received.lock().unwrap().push(Received {
+            hints,
+            request: request.clone(),
+        });
+        tokio::time::sleep(self.delay).await;
+        if let Some(code) = self.failures.lock().unwrap().pop_front() {
+            return Err(Status::new(code, "injected failure"));
+        }
+        let rows = count_rows(request)?;
+        Ok(affected_rows(rows))
+    }
+
+    /// Answers with the number of rows in all requests of the stream.
+    async fn handle_requests(
+        &self,
+        request: tonic::Request<tonic::Streaming<GreptimeRequest>>,
+    ) -> Result<Response<GreptimeResponse>, Status> {
+        let mut requests = request.into_inner();
+        let mut rows = 0;
+        while let Some(request) = requests.message().await? {
+            rows += count_rows(request)?;
+        }
+        Ok(affected_rows(rows))
+    }
+}
+
+type BoxStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+/// Acknowledges every bulk write message with the number of rows in it, and
+/// counts the record batches received.
+struct StubFlight {
+    batches: Arc<AtomicUsize>,
+}
+
+#[tonic::async_trait]
+impl FlightService for StubFlight {
+    type HandshakeStream = BoxStream<HandshakeResponse>;
+    type ListFlightsStream = BoxStream<FlightInfo>;
+    type DoGetStream = BoxStream<FlightData>;
+    type DoPutStream = BoxStream<PutResult>;
+    type DoExchangeStream = BoxStream<FlightData>;
+    type DoActionStream = BoxStream<arrow_flight::Result>;
+    type ListActionsStream = BoxStream<ActionType>;
+
+    async fn handshake(
+        &self,
+        _: tonic::Request<tonic::Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("handshake"))
+    }
+
+    async fn list_flights(
+        &self,
+        _: tonic::Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        Err(Status::unimplemented("list_flights"))
+    }
+
+    async fn get_flight_info(
+        &self,
+        _: tonic::Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        Err(Status::unimplemented("get_flight_info"))
+    }
+
+    async fn poll_flight_info(
+        &self,
+        _: tonic::Request<FlightDescriptor>,
+    ) -> Result<Response<PollInfo>, Status> {
+        Err(Status::unimplemented("poll_flight_info"))
+    }
+
+    async fn get_schema(
+        &self,
+        _: tonic::Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        Err(Status::unimplemented("get_schema"))
+    }
+
+    async fn do_get(
+        &self,
+        _: tonic::Request<Ticket>,
+    ) -> Result<Response<Self::DoGetStream>, Status> {
+        Err(Status::unimplemented("do_get"))
+    }
+
+    async fn do_put(
+        &self,
+        request: tonic::Request<tonic::Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        let batches = self.batches.clone();
+        let stream = request.into_inner().map_err(FlightError::from);
+        let results = FlightDataDecoder::new(stream).map(move |data| {
+            let data = data.map_err(|e| Status::invalid_argument(e.to_string()))?;
+            let metadata: serde_json::Value = serde_json::from_slice(&data.inner.app_metadata)
+                .map_err(|e| Status::invalid_argument(e.to_string()))?;
+            let rows = match data.payload {
+                DecodedPayload::RecordBatch(batch) => {
+                    batches.fetch_add(1, Ordering::SeqCst);
+                    batch.num_rows()
+                }
+                _ => 0,
+            };
+            let metadata = serde_json::json!({
+                "request_id": metadata["request_id"],
+                "affected_rows": rows,
+            });
+            Ok(PutResult {
+                app_metadata: serde_json::to_vec(&metadata).unwrap().into(),
+            })
+        });
+        Ok(Response::new(Box::pin(results)))
+    }
+
+    async fn do_exchange(
+        &self,
+        _: tonic::Request<tonic::Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("do_exchange"))
+    }
+
+    async fn do_action(
+        &self,
+        _: tonic::Request<Action>,
+    ) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("do_action"))
+    }
+
+    async fn list_actions(
+        &self,
+        _: tonic::Request<Empty>,
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        Err(Status::unimplemented("list_actions"))
+    }
+}
+
+fn count_rows(request: GreptimeRequest) -> Result<usize, Status> {
+    let rows = match request.request {
+        Some(Request::RowInserts(inserts)) => inserts
+            .inserts
+            .iter()
+            .map(|insert| insert.rows.as_ref().map_or(0, |rows| rows.rows.len()))
+            .sum(),
+        Some(Request::RowDeletes(deletes)) => deletes
+            .deletes
+            .iter()
+            .map(|delete| delete.rows.as_ref().map_or(0, |rows| rows.rows.len()))
+            .sum(),
+        _ => return Err(Status::unimplemented("unsupported request")),
+    };
+    Ok(rows)
+}
+
+fn affected_rows(rows: usize) -> Response<GreptimeResponse> {
+    Response::new(GreptimeResponse {
+        header: None,
+        response: Some(greptime_response::Response::AffectedRows(AffectedRows {
+            value: rows as u32,
+        })),
+    })
+}
+
+#[derive(Default)]
+pub struct StubOptions {
+    /// Serves with TLS if given.
+    pub identity: Option<Identity>,
+    /// Requires client certificates issued by the CA if given.
+    pub client_ca: Option<Certificate>,
+    /// Delay of every response.
+    pub delay: Duration,
+    /// Codes that the first requests fail with, one per request.
+    pub failures: Vec<Code>,
+}
+
+/// Serves [StubDatabase] and [StubFlight] on a random local port until dropped.
+pub struct StubServer {
+    _runtime: Runtime,
+    addr: SocketAddr,
+    received: Arc<Mutex<Vec<Received>>>,
+    batches: Arc<AtomicUsize>,
+}
+
+impl StubServer {
+    pub fn start(options: StubOptions) -> Self {
+        let runtime = Runtime::new().unwrap();
+        let listener = runtime.block_on(TcpListener::bind("127.0.0.1:0")).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut server = Server::builder();
+        if let Some(identity) = options.identity {
+            let mut tls = ServerTlsConfig::new().identity(identity);
+            if let Some(client_ca) = options.client_ca {
+                tls = tls.client_ca_root(client_ca);
+            }
+            server = server.tls_config(tls).unwrap();
+        }
+        let received = Arc::new(Mutex::new(vec![]));
+        let batches = Arc::new(AtomicUsize::new(0));
+        let router = server
+            .add_service(GreptimeDatabaseServer::new(StubDatabase {
+                delay: options.delay,
+                failures: Mutex::new(options.failures.into()),
+                received: received.clone(),
+            }))
+            .add_service(FlightServiceServer::new(StubFlight {
+                batches: batches.clone(),
+            }));
+        drop(runtime.spawn(router.serve_with_incoming(TcpListenerStream::new(listener))));
+
+        Self {
+            _runtime: runtime,
+            addr,
+            received,
+            batches,
+        }
+    }
+
+    pub fn addr(&self) -> String {
+        self.addr.to_string()
+    }
+
+    /// Returns all unary requests received so far, including failed ones.
+    pub fn received(&self) -> Vec<Received> {
+        self.received.lock().unwrap().clone()
+    }
+
+    /// Returns the number of record batches received by bulk writes.
+    pub fn batches(&self) -> usize {
+        self.batches.load(Ordering::SeqCst)
+    }
+}
+
+/// Creates a client of database `public` connecting to `endpoint`.
+pub fn new_client(endpoint: &str) -> Client {
+    let mut options = ClientOptions::new("public".to_string());
+    options.add_endpoint(endpoint.to_string());
+    Client::new(&options).unwrap()
+}
@@ -0,0 +1,415 @@
+// Copyright 2023 Greptime Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::endpoint::Endpoints;
+use crate::row::RowBuilder;
+use crate::{Client, error, insert};
+use greptimedb_ingester::api::v1::{RowInsertRequest, RowInsertRequests};
+use prost::Message;
+use tokio::runtime::Handle;
+use tokio::sync::{Notify, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::{Interval, MissedTickBehavior};
+use tracing::{debug, info};
+
+/// Flush thresholds of [BatchWriter], 0 disables the corresponding trigger.
+#[repr(C)]
+pub struct BatchWriterOptions {
+    max_rows: usize,
+    max_bytes: usize,
+    flush_interval_ms: u64,
+}
+
+type FlushCallback = Box<dyn Fn(error::Result<u32>) + Send + Sync>;
+
+/// Buffers rows of many row builders and writes them from a background task
+/// once any of the thresholds is reached. Buffered rows are flushed when the
+/// writer is dropped. Rows of a failed flush are discarded, the failure is
+/// only reported to the flush callback.
+pub struct BatchWriter {
+    inner: Arc<Inner>,
+    runtime: Handle,
+    shutdown: Option<oneshot::Sender<()>>,
+    flush_task: Option<JoinHandle<()>>,
+}
+
+struct Inner {
+    endpoints: Endpoints,
+    timeout: Duration,
+    max_rows: usize,
+    max_bytes: usize,
+    buffer: Mutex<Buffer>,
+    notify: Notify,
+    on_flush: FlushCallback,
+}
+
+/// Buffered rows of builders with the same hints, which are written in one
+/// request with these hints.
+struct Group {
+    hints: Vec<(String, String)>,
+    inserts: Vec<RowInsertRequest>,
+}
+
+#[derive(Default)]
+struct Buffer {
+    groups: Vec<Group>,
+    rows: usize,
+    bytes: usize,
+}
+
+impl Buffer {
+    /// Drains rows of the builder into the buffer, rows of the same table,
+    /// schema and hints are merged into one request.
+    fn append(&mut self, row: &mut RowBuilder) {
+        let group = match self
+            .groups
+            .iter()
+            .position(|g| row.has_same_hints(&g.hints))
+        {
+            Some(i) => i,
+            None => {
+                let hints = row
+                    .hints()
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect();
+                self.groups.push(Group {
+                    hints,
+                    inserts: vec![],
+                });
+                self.groups.len() - 1
+            }
+        };
+        let req = RowInsertRequest::from(row);
+        let rows = match req.rows {
+            Some(rows) if !rows.rows.is_empty() => rows,
+            _ => {
+                // Hints of a builder without rows are not kept.
+                if self.groups[group].inserts.is_empty() {
+                    self.groups.remove(group);
+                }
+                return;
+            }
+        };
+        self.rows += rows.rows.len();
+        self.bytes += rows.rows.iter().map(Message::encoded_len).sum::<usize>();
+
+        let inserts = &mut self.groups[group].inserts;
+        let existing = inserts.iter_mut().find(|r| {
+            r.table_name == req.table_name
+                && r.rows.as_ref().is_some_and(|r| r.schema == rows.schema)
+        });
+        match existing.and_then(|r| r.rows.as_mut()) {
+            Some(existing) => existing.rows.extend(rows.rows),
+            None => inserts.push(RowInsertRequest {
+                table_name: req.table_name,
+                rows: Some(rows),
+            }),
+        }
+    }
+
+    /// Takes buffered rows as one request per group of hints.
+    fn take(&mut self) -> Vec<(RowInsertRequests, Vec<(String, String)>)> {
+        std::mem::take(self)
+            .groups
+            .into_iter()
+            .map(|group| {
+                let inserts = RowInsertRequests {
+                    inserts: group.inserts,
+                };
+                (inserts, group.hints)
+            })
+            .collect()
+    }
+}
+
+impl Inner {
+    /// Writes and takes all buffered rows, which are not buffered again if it fails.
+    /// Rows of each group of hints are written in their own request, all of them
+    /// are sent even if some fail, and the first failure is returned.
+    async fn flush(&self) -> error::Result<u32> {
+        let requests = self.buffer.lock().unwrap().take();
+        let mut affected_rows = 0;
+        let mut first_err = None;
+        for (insert_reqs, hints) in requests {
+            match insert(&self.endpoints, insert_reqs, &hints, self.timeout, &mut 0).await {
+                Ok(rows) => affected_rows += rows,
+                Err(e) => {
+                    first_err.get_or_insert(e);
+                }
+            }
+        }
+        match first_err {
+            Some(e) => Err(e),
+            None => Ok(affected_rows),
+        }
+    }
+
+    fn should_flush(&self, buffer: &Buffer) -> bool {
+        (self.max_rows > 0 && buffer.rows >= self.max_rows)
+            || (self.max_bytes > 0 && buffer.bytes >= self.max_bytes)
+    }
+}
+
+impl BatchWriter {
+    pub fn new(
+        client: &Client,
+        options: &BatchWriterOptions,
+        on_flush: impl Fn(error::Result<u32>) + Send + Sync + 'static,
+    ) -> Self {
+        let inner = Arc::new(Inner {
+            endpoints: client.endpoints.clone(),
+            timeout: client.timeout,
+            max_rows: options.max_rows,
+            max_bytes: options.max_bytes,
+            buffer: Mutex::new(Buffer::default()),
+            notify: Notify::new(),
+            on_flush: Box::new(on_flush),
+        });
+        let (tx, rx) = oneshot::channel();
+        let runtime = client.runtime.handle().clone();
+        let flush_interval = Duration::from_millis(options.flush_interval_ms);
+        let flush_task = runtime.spawn(run_flush_loop(inner.clone(), flush_interval, rx));
+
+        Self {
+            inner,
+            runtime,
+            shutdown: Some(tx),
+            flush_task: Some(flush_task),
+        }
+    }
+
+    /// Drains rows of the builder into the buffer, the builder can be reused
+    /// once it returns. Rows are written with the hints of their builder.
+    pub fn write(&self, row: &mut RowBuilder) -> error::Result<()> {
+        let mut buffer = self.inner.buffer.lock().unwrap();
+        buffer.append(row);
+        if self.inner.should_flush(&buffer) {
+            self.inner.notify.notify_one();
+        }
+        Ok(())
+    }
+
+    /// Writes all buffered rows and returns the number of affected rows.
+    pub fn flush(&self) -> error::Result<u32> {
+        self.runtime.block_on(self.inner.flush())
+    }
+}
+
+impl Drop for BatchWriter {
+    fn drop(&mut self) {
+        info!("Dropping batch writer");
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(flush_task) = self.flush_task.take() {
+            // The flush loop writes buffered rows before it exits.
+            let _ = self.runtime.block_on(flush_task);
+        }
+    }
+}
+
+async fn run_flush_loop(
+    inner: Arc<Inner>,
+    flush_interval: Duration,
+    mut shutdown: oneshot::Receiver<()>,
+) {
+    let mut ticker = (!flush_interval.is_zero()).then(|| {
+        let mut ticker = tokio::time::interval(flush_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ticker
+    });
+    loop {
+        let stopped = tokio::select! {
+            _ = &mut shutdown => true,
+            _ = inner.notify.notified() => false,
+            _ = tick(&mut ticker) => false,
+        };
+        if inner.buffer.lock().unwrap().rows > 0 {
+            debug!("Flushing batch writer, stopped: {}", stopped);
+            (inner.on_flush)(inner.flush().await);
+        }
+        if stopped {
+            break;
+        }
+    }
+}
+
+async fn tick(ticker: &mut Option<Interval>) {
+    match ticker {
+        Some(ticker) => {
+            ticker.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::row::Value;
+    use crate::test_util;
+    use greptimedb_ingester::SemanticType;
+    use greptimedb_ingester::api::v1::ColumnDataType;
+
+    fn builder(table: &str, col: &str) -> RowBuilder {
+        let mut builder = RowBuilder::new(table.to_string());
+        builder
+            .add_col(
+                col.to_string(),
+                ColumnDataType::Int64 as i32,
+                SemanticType::Field as i32,
+            )
+            .unwrap();
+        builder
+    }
+
+    #[test]
+    fn buffer_merges_rows_of_same_table_and_schema() {
+        let mut buffer = Buffer::default();
+        let mut a = builder("a", "v");
+        let mut a2 = builder("a", "other");
+        let mut b = builder("b", "v");
+        let mut append = |builder: &mut RowBuilder, n: i64| {
+            for i in 0..n {
+                unsafe { builder.add_row(&[Value { i64_value: i }]).unwrap() };
+            }
+            buffer.append(builder);
+        };
+        append(&mut a, 2);
+        append(&mut a2, 1);
+        append(&mut b, 1);
+        append(&mut a, 1);
+        // Appending a drained builder is a no-op.
+        buffer.append(&mut a);
+
+        assert_eq!(buffer.rows, 5);
+        assert!(buffer.bytes > 0);
+        let mut requests = buffer.take();
+        assert_eq!(requests.len(), 1);
+        let (reqs, hints) = requests.pop().unwrap();
+        assert!(hints.is_empty());
+        let rows: Vec<_> = reqs
+            .inserts
+            .iter()
+            .map(|r| (r.table_name.as_str(), r.rows.as_ref().unwrap().rows.len()))
+            .collect();
+        assert_eq!(rows, vec![("a", 3), ("a", 1), ("b", 1)]);
+        assert_eq!(buffer.rows, 0);
+        assert!(buffer.groups.is_empty());
+    }
+
+    #[test]
+    fn buffer_groups_rows_by_hints() {
+        let mut buffer = Buffer::default();
+        let mut a = builder("a", "v");
+        let mut b = builder("b", "v");
+        let mut c = builder("c", "v");
+        a.set_hint("ttl".to_string(), "7d".to_string()).unwrap();
+        a.set_hint("append_mode".to_string(), "true".to_string())
+            .unwrap();
+        b.set_hint("ttl".to_string(), "1d".to_string()).unwrap();
+        c.set_hint("append_mode".to_string(), "true".to_string())
+            .unwrap();
+        c.set_hint("ttl".to_string(), "7d".to_string()).unwrap();
+
+        // Hints of a builder without rows are not buffered.
+        buffer.append(&mut b);
+        assert!(buffer.groups.is_empty());
+        for builder in [&mut a, &mut b, &mut c] {
+            unsafe { builder.add_row(&[Value { i64_value: 1 }]).unwrap() };
+            buffer.append(builder);
+        }
+
+        let requests: Vec<_> = buffer
+            .take()
+            .into_iter()
+            .map(|(reqs, hints)| {
+                let tables: Vec<_> = reqs.inserts.into_iter().map(|r| r.table_name).collect();
+                (tables, hints)
+            })
+            .collect();
+        let hint = |k: &str, v: &str| (k.to_string(), v.to_string());
+        assert_eq!(
+            requests,
+            vec![
+                (
+                    vec!["a".to_string(), "c".to_string()],
+                    vec![hint("ttl", "7d"), hint("append_mode", "true")]
+                ),
+                (vec!["b".to_string()], vec![hint("ttl", "1d")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn batch_writer_flushes_each_group_of_hints_in_its_own_request() {
+        let server = test_util::StubServer::start(Default::default());
+        let client = test_util::new_client(&server.addr());
+        let options = BatchWriterOptions {
+            max_rows: 0,
+            max_bytes: 0,
+            flush_interval_ms: 0,
+        };
+        let writer = BatchWriter::new(&client, &options, |_| {});
+
+        let mut a = builder("a", "v");
+        let mut b = builder("b", "v");
+        a.set_hint("ttl".to_string(), "7d".to_string()).unwrap();
+        for (builder, rows) in [(&mut a, 2), (&mut b, 1)] {
+            for i in 0..rows {
+                unsafe { builder.add_row(&[Value { i64_value: i }]).unwrap() };
+            }
+            writer.write(builder).unwrap();
+        }
+        assert_eq!(writer.flush().unwrap(), 3);
+
+        let hints: Vec<_> = server.received().into_iter().map(|r| r.hints).collect();
+        assert_eq!(hints, [Some("ttl=7d".to_string()), None]);
+    }
+
+    #[test]
+    fn batch_writer_flushes_in_background_and_on_drop() {
+        // Nothing listens on port 1, so every flush fails once it is sent.
+        let client = test_util::new_client("127.0.0.1:1");
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx = Mutex::new(tx);
+        let options = BatchWriterOptions {
+            max_rows: 2,
+            max_bytes: 0,
+            flush_interval_ms: 0,
+        };
+        let writer = BatchWriter::new(&client, &options, move |res| {
+            tx.lock().unwrap().send(res.is_err()).unwrap()
+        });
+
+        let mut row = builder("a", "v");
+        for i in 0..2 {
+            unsafe { row.add_row(&[Value { i64_value: i }]).unwrap() };
+        }
+        writer.write(&mut row).unwrap();
+        assert!(rx.recv_timeout(Duration::from_secs(10)).unwrap());
+        // Rows of the failed flush are discarded.
+        assert_eq!(writer.inner.buffer.lock().unwrap().rows, 0);
+
+        unsafe { row.add_row(&[Value { i64_value: 3 }]).unwrap() };
+        writer.write(&mut row).unwrap();
+        assert!(rx.try_recv().is_err());
+        drop(writer);
+        assert!(rx.try_recv().unwrap());
+    }
+}